@@ -0,0 +1,183 @@
+/// Iterator over every way of picking `size` items from a slice.
+///
+/// By default each item is picked at most once and the order of the picked items does not
+/// matter, so `[a, b]` and `[b, a]` are the same combination. `with_replacement` allows
+/// the same item to be picked more than once, and `ordered` yields every ordering of a
+/// combination as a different item.
+///
+/// Items are yielded in lexicographic order of their indexes in the slice.
+pub struct Combinations<'a, T> {
+    items: &'a [T],
+    indexes: Vec<usize>,
+    replacement: bool,
+    ordered: bool,
+    started: bool,
+    done: bool
+}
+
+impl<'a, T> Combinations<'a, T> {
+
+    pub fn new(items: &'a [T], size: usize) -> Combinations<'a, T> {
+        Combinations {
+            items,
+            indexes: vec![0; size],
+            replacement: false,
+            ordered: false,
+            started: false,
+            done: false
+        }
+    }
+
+    /// Allow the same item to be picked more than once in a combination
+    pub fn with_replacement(mut self) -> Combinations<'a, T> {
+        self.replacement = true;
+        self
+    }
+
+    /// Yield every ordering of the picked items instead of only one
+    pub fn ordered(mut self) -> Combinations<'a, T> {
+        self.ordered = true;
+        self
+    }
+
+    /// Indexes of the items in the last combination yielded
+    pub fn indexes(&self) -> &[usize] {
+        &self.indexes
+    }
+
+    /// Can the item at `index` be picked at `position`, given the items picked before it
+    fn allowed(&self, position: usize, index: usize) -> bool {
+        let previous = &self.indexes[..position];
+        match (self.ordered, self.replacement) {
+            (true, true) => true,
+            (true, false) => !previous.contains(&index),
+            (false, true) => previous.last().is_none_or(|p| index >= *p),
+            (false, false) => previous.last().is_none_or(|p| index > *p)
+        }
+    }
+
+    /// Pick the smallest allowed index for every position from `start` onwards.
+    /// Returns false when there aren't enough items left to fill all positions.
+    fn fill(&mut self, start: usize) -> bool {
+        for position in start..self.indexes.len() {
+            match (0..self.items.len()).find(|i| self.allowed(position, *i)) {
+                Some(index) => self.indexes[position] = index,
+                None => return false
+            }
+        }
+        true
+    }
+
+    /// Move to the next combination, like an odometer: bump the rightmost position that
+    /// can still be increased and reset everything to its right.
+    fn advance(&mut self) -> bool {
+        let mut position = self.indexes.len();
+        while position > 0 {
+            position -= 1;
+            let next = (self.indexes[position] + 1..self.items.len())
+                .find(|i| self.allowed(position, *i));
+
+            if let Some(index) = next {
+                self.indexes[position] = index;
+                if self.fill(position + 1) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+impl<'a, T> Iterator for Combinations<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        if self.done {
+            return None;
+        }
+
+        let found = if self.started {
+            self.advance()
+        } else {
+            self.started = true;
+            self.fill(0)
+        };
+
+        if !found {
+            self.done = true;
+            return None;
+        }
+
+        let items = self.indexes.iter()
+            .map(|i| &self.items[*i])
+            .collect();
+        Some(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(combinations: Combinations<char>) -> Vec<String> {
+        combinations
+            .map(|items| items.into_iter().collect())
+            .collect()
+    }
+
+    const ITEMS: [char; 3] = ['a', 'b', 'c'];
+
+    #[test]
+    fn unordered_without_replacement() {
+        assert_eq!(collect(Combinations::new(&ITEMS, 2)), vec!["ab", "ac", "bc"]);
+        assert_eq!(collect(Combinations::new(&ITEMS, 3)), vec!["abc"]);
+    }
+
+    #[test]
+    fn unordered_with_replacement() {
+        assert_eq!(
+            collect(Combinations::new(&ITEMS, 2).with_replacement()),
+            vec!["aa", "ab", "ac", "bb", "bc", "cc"]
+        );
+    }
+
+    #[test]
+    fn ordered_without_replacement() {
+        assert_eq!(
+            collect(Combinations::new(&ITEMS, 2).ordered()),
+            vec!["ab", "ac", "ba", "bc", "ca", "cb"]
+        );
+    }
+
+    #[test]
+    fn ordered_with_replacement() {
+        assert_eq!(
+            collect(Combinations::new(&ITEMS, 2).ordered().with_replacement()),
+            vec!["aa", "ab", "ac", "ba", "bb", "bc", "ca", "cb", "cc"]
+        );
+    }
+
+    #[test]
+    fn size_zero_yields_one_empty_combination() {
+        assert_eq!(collect(Combinations::new(&ITEMS, 0)), vec![""]);
+        assert_eq!(collect(Combinations::new(&ITEMS, 0).ordered().with_replacement()), vec![""]);
+        assert_eq!(collect(Combinations::new(&[], 0)), vec![""]);
+    }
+
+    #[test]
+    fn size_above_length() {
+        assert!(collect(Combinations::new(&ITEMS, 4)).is_empty());
+        assert!(collect(Combinations::new(&ITEMS, 4).ordered()).is_empty());
+        assert_eq!(collect(Combinations::new(&['a', 'b'], 3).with_replacement()), vec!["aaa", "aab", "abb", "bbb"]);
+        assert_eq!(collect(Combinations::new(&['a', 'b'], 3).ordered().with_replacement()).len(), 8);
+        assert!(collect(Combinations::new(&[], 1).with_replacement()).is_empty());
+    }
+
+    #[test]
+    fn indexes_follow_the_last_combination() {
+        let mut combinations = Combinations::new(&ITEMS, 2);
+        combinations.next();
+        combinations.next();
+        assert_eq!(combinations.indexes(), &[0, 2]);
+    }
+}
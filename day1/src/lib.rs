@@ -0,0 +1,3 @@
+mod combinations;
+
+pub use combinations::Combinations;
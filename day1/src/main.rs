@@ -2,109 +2,458 @@ use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Display};
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::process;
+use std::str::FromStr;
 
-/// Container that will reset its value to 0 when it goes over a maximum value
-struct Index {
-    max: usize,
-    value: usize
+/// Integer types that entries can be parsed as
+trait Number: Copy + Ord + FromStr + Display + Debug {
+    const ONE: Self;
+
+    /// Type used to add entries together while searching
+    type Wide: Wide;
+
+    fn checked_mul(self, other: Self) -> Option<Self>;
+
+    fn widen(self) -> Self::Wide;
 }
 
-/// Generate an iterator of all possible permutations for indexes in a Vec.
-/// It isn't the most efficient algorithm, but it's the one that came to mind.
-/// Afterwards I checked wikipedia and discovered that there's a much more efficient
-/// algorithm for generating permutations, but hey this just for fun !
-struct Permutation {
-    indexes: Vec<Index>,
+/// Integer types that sums of entries are computed with
+trait Wide: Copy + Ord + Hash + Debug {
+    const ZERO: Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    fn checked_sub(self, other: Self) -> Option<Self>;
+
+    fn saturating_add(self, other: Self) -> Self;
+}
+
+macro_rules! number {
+    ($($t:ty => $wide:ty),*) => {
+        $(
+            impl Number for $t {
+                const ONE: Self = 1;
+
+                type Wide = $wide;
+
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, other)
+                }
+
+                fn widen(self) -> $wide {
+                    <$wide>::from(self)
+                }
+            }
+        )*
+    }
 }
 
-impl Index {
+macro_rules! wide {
+    ($($t:ty),*) => {
+        $(
+            impl Wide for $t {
+                const ZERO: Self = 0;
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
 
-    fn new (max: usize) -> Index {
-        Index {
-            max: max,
-            value: 0
+                fn checked_sub(self, other: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, other)
+                }
+
+                fn saturating_add(self, other: Self) -> Self {
+                    <$t>::saturating_add(self, other)
+                }
+            }
+        )*
+    }
+}
+
+// a handful of 64 bit numbers cannot overflow an i128, so only u128 sums are ever checked
+number!(u64 => i128, i64 => i128, u128 => u128);
+wide!(i128, u128);
+
+#[derive(Debug, PartialEq)]
+enum Error<T> {
+    /// adding these entries together overflows
+    Sum { indexes: Vec<usize>, values: Vec<T> },
+    /// multiplying these entries together overflows
+    Product { indexes: Vec<usize>, values: Vec<T> }
+}
+
+impl<T: Number> Display for Error<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Sum { indexes, values } => write!(f, "sum of {:?} at indexes {:?} overflows", values, indexes),
+            Error::Product { indexes, values } => write!(f, "product of {:?} at indexes {:?} overflows", values, indexes)
         }
     }
+}
 
-    /// Increment the value and return true if it has overflowed to 0
-    fn increment(&mut self) -> bool {
-        self.value += 1;
-        if self.value == self.max {
-            self.value = 0;
-            true
-        } else {
-            false
+/// Result of a step of the search: whether to keep searching, or the positions of
+/// entries whose sum overflows
+type Step = Result<bool, Vec<usize>>;
+
+/// Hasher for the sums of pairs. Sums are plain integers and nothing is hashed from outside
+/// the input, so a multiply and rotate is enough, and much faster than the default SipHash.
+#[derive(Default)]
+struct SumHasher(u64);
+
+impl Hasher for SumHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_le_bytes(word));
         }
     }
 
+    fn write_u64(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_u128(&mut self, word: u128) {
+        self.write_u64(word as u64);
+        self.write_u64((word >> 64) as u64);
+    }
+
+    fn write_i128(&mut self, word: i128) {
+        self.write_u128(word as u128);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Positions of pairs of entries, grouped by their sum. Pairs with the same sum are chained
+/// through a single list, instead of each sum owning its own vector.
+struct PairSums<W> {
+    /// first pair of each sum
+    heads: HashMap<W, u32, BuildHasherDefault<SumHasher>>,
+    /// first and second positions of each pair, and the next pair with the same sum
+    pairs: Vec<(u32, u32, u32)>
+}
+
+/// Marks the end of a chain of pairs
+const END: u32 = u32::MAX;
+
+impl<W: Wide> PairSums<W> {
+
+    fn insert(&mut self, sum: W, first: usize, second: usize) {
+        let index = self.pairs.len() as u32;
+        let next = self.heads.insert(sum, index).unwrap_or(END);
+        self.pairs.push((first as u32, second as u32, next));
+    }
+
+    fn get(&self, sum: W) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut index = self.heads.get(&sum).copied().unwrap_or(END);
+        std::iter::from_fn(move || {
+            let (first, second, next) = *self.pairs.get(index as usize)?;
+            index = next;
+            Some((first as usize, second as usize))
+        })
+    }
 }
 
-impl Permutation {
+impl<W> Default for PairSums<W> {
+    fn default() -> PairSums<W> {
+        PairSums { heads: HashMap::default(), pairs: Vec::new() }
+    }
+}
+
+/// Finds combinations of entries that add up to a target sum.
+///
+/// Entries are sorted once so that pairs can be found with two pointers walking
+/// towards each other. Groups of four are split in two pairs that meet in a table
+/// of pair sums. Any other size fixes its smallest entry and searches for the rest.
+///
+/// Entries are widened to the `Wide` type of their number type while searching.
+/// Sums that still overflow it stop the search with an error naming their entries.
+struct KSum<'a, T: Number> {
+    numbers: &'a [T],
+    /// entries sorted by value, along with their index in the original list
+    entries: Vec<(T::Wide, usize)>
+}
 
-    fn new(size: usize, max: usize) -> Permutation {
-        Permutation {
-            indexes: (0..size).map(|_| Index::new(max)).collect()
+impl<'a, T: Number> KSum<'a, T> {
+
+    fn new(numbers: &'a [T]) -> KSum<'a, T> {
+        let mut entries: Vec<(T::Wide, usize)> = numbers.iter()
+            .enumerate()
+            .map(|(index, value)| (value.widen(), index))
+            .collect();
+        entries.sort_unstable();
+        KSum { numbers, entries }
+    }
+
+    /// Call `found` with the indexes of every combination of `size` entries that sum up to `target`.
+    /// Indexes are given in ascending order. The search stops as soon as `found` returns false.
+    fn search<F>(&self, size: usize, target: T, mut found: F) -> Result<(), Error<T>>
+        where F: FnMut(&[usize]) -> bool
+    {
+        let mut chosen = Vec::with_capacity(size);
+        self.combine(0, size, target.widen(), &mut chosen, &mut found)
+            .map(|_| ())
+            .map_err(|positions| {
+                let mut indexes: Vec<usize> = positions.iter().map(|p| self.entries[*p].1).collect();
+                indexes.sort_unstable();
+                let values = indexes.iter().map(|i| self.numbers[*i]).collect();
+                Error::Sum { indexes, values }
+            })
+    }
+
+    fn value(&self, position: usize) -> T::Wide {
+        self.entries[position].0
+    }
+
+    /// Search for `size` entries in the sorted list, starting at position `start`.
+    /// Returns false when the search has been stopped.
+    fn combine<F>(&self, start: usize, size: usize, target: T::Wide, chosen: &mut Vec<usize>, found: &mut F) -> Step
+        where F: FnMut(&[usize]) -> bool
+    {
+        if self.entries.len() < start + size {
+            return Ok(true);
+        }
+
+        match size {
+            0 => Ok(target != T::Wide::ZERO || self.report(chosen, found)),
+            1 => Ok(self.single(start, target, chosen, found)),
+            2 => self.pairs(start, target, chosen, found),
+            4 => Ok(self.quads(start, target, chosen, found)),
+            _ => self.fix_smallest(start, size, target, chosen, found)
         }
     }
 
-    /// Increment all indexes in the permutation. Return true if the last index has overflowed back to 0.
-    /// when the last index has overflowed, then we have iterated through all possible permutations.
-    fn increment(&mut self) -> bool {
-        let mut index = 0;
-        loop {
-            if !self.indexes[index].increment() {
-                return false;
+    fn single<F>(&self, start: usize, target: T::Wide, chosen: &mut Vec<usize>, found: &mut F) -> bool
+        where F: FnMut(&[usize]) -> bool
+    {
+        for position in start..self.entries.len() {
+            if self.value(position) == target {
+                chosen.push(position);
+                let more = self.report(chosen, found);
+                chosen.pop();
+                if !more {
+                    return false;
+                }
             }
+        }
+        true
+    }
+
+    /// Walk two pointers from both ends of the list. Runs of equal values are expanded
+    /// so that every combination of indexes is found, not only every combination of values.
+    fn pairs<F>(&self, start: usize, target: T::Wide, chosen: &mut Vec<usize>, found: &mut F) -> Step
+        where F: FnMut(&[usize]) -> bool
+    {
+        let mut low = start;
+        let mut high = self.entries.len() - 1;
 
-            if index == self.indexes.len() - 1 {
-                return true;
+        while low < high {
+            let (left, right) = (self.value(low), self.value(high));
+            let sum = left.checked_add(right).ok_or_else(|| {
+                let mut positions = chosen.clone();
+                positions.extend_from_slice(&[low, high]);
+                positions
+            })?;
+
+            if sum < target {
+                low += 1;
+            } else if sum > target {
+                high -= 1;
+            } else if left == right {
+                for first in low..high {
+                    for second in first + 1..=high {
+                        if !self.report_all(chosen, &[first, second], found) {
+                            return Ok(false);
+                        }
+                    }
+                }
+                return Ok(true);
+            } else {
+                let low_end = (low..high).find(|p| self.value(*p) != left).unwrap_or(high);
+                let high_start = (low_end..=high).find(|p| self.value(*p) == right).unwrap_or(high);
+                for first in low..low_end {
+                    for second in high_start..=high {
+                        if !self.report_all(chosen, &[first, second], found) {
+                            return Ok(false);
+                        }
+                    }
+                }
+                low = low_end;
+                high = high_start - 1;
             }
-            index += 1;
         }
+        Ok(true)
     }
 
-    /// Used to make sure we don't accidentally combine the same index more than once
-    fn has_duplicates(&self) -> bool {
-        for i in 0..self.indexes.len() {
-            for j in 0..self.indexes.len() {
-                if i != j && self.indexes[i].value == self.indexes[j].value {
-                    return true;
+    /// Meet in the middle: while walking the third entry forward, keep a table of the sums of every
+    /// pair that comes before it. Each (third, fourth) pair then only needs a lookup for the rest.
+    /// Sums are only compared with what is left of the target, so they never overflow.
+    fn quads<F>(&self, start: usize, target: T::Wide, chosen: &mut Vec<usize>, found: &mut F) -> bool
+        where F: FnMut(&[usize]) -> bool
+    {
+        let mut sums = PairSums::default();
+        // no pair can sum to less than the two smallest entries
+        let smallest = self.value(start).saturating_add(self.value(start + 1));
+
+        for third in start..self.entries.len() {
+            for fourth in third + 1..self.entries.len() {
+                // what is left of the target only gets smaller as the fourth entry grows
+                let needed = target.checked_sub(self.value(third))
+                    .and_then(|rest| rest.checked_sub(self.value(fourth)));
+                let needed = match needed {
+                    Some(needed) if needed >= smallest => needed,
+                    _ => break
+                };
+                for (first, second) in sums.get(needed) {
+                    if !self.report_all(chosen, &[first, second, third, fourth], found) {
+                        return false;
+                    }
                 }
             }
+
+            // later pairs only have bigger values, so sums above this limit can never be needed
+            let limit = target.checked_sub(self.value(third))
+                .and_then(|rest| rest.checked_sub(self.value(third)));
+            let limit = match limit {
+                Some(limit) => limit,
+                None => continue
+            };
+            for first in start..third {
+                let sum = self.value(first).saturating_add(self.value(third));
+                if sum > limit {
+                    break;
+                }
+                sums.insert(sum, first, third);
+            }
         }
-        false
+        true
     }
-}
 
-impl Iterator for Permutation {
-    type Item = Vec<usize>;
+    fn fix_smallest<F>(&self, start: usize, size: usize, target: T::Wide, chosen: &mut Vec<usize>, found: &mut F) -> Step
+        where F: FnMut(&[usize]) -> bool
+    {
+        // bounds used to skip entries, saturating is enough to compare them with the target
+        let sum = |positions: std::ops::Range<usize>| positions
+            .map(|p| self.value(p))
+            .fold(T::Wide::ZERO, |total, value| total.saturating_add(value));
+
+        let len = self.entries.len();
+        let largest = sum(len + 1 - size..len);
 
-    fn next(&mut self) -> Option<Vec<usize>> {
-        loop {
-            if self.increment() {
-                return None;
+        for position in start..=len - size {
+            if sum(position..position + size) > target {
+                break;
             }
-            if !self.has_duplicates() {
-                let values = self.indexes.iter().map(|i| i.value).collect();
-                return Some(values);
+            if self.value(position).saturating_add(largest) < target {
+                continue;
+            }
+
+            // entries are sorted, so an unsigned rest can only be missing once the smallest
+            // entries are already above the target, which ended the loop above
+            let rest = match target.checked_sub(self.value(position)) {
+                Some(rest) => rest,
+                None => break
+            };
+
+            chosen.push(position);
+            let more = self.combine(position + 1, size - 1, rest, chosen, found);
+            chosen.pop();
+            if !more? {
+                return Ok(false);
             }
         }
+        Ok(true)
+    }
+
+    fn report_all<F>(&self, chosen: &mut Vec<usize>, positions: &[usize], found: &mut F) -> bool
+        where F: FnMut(&[usize]) -> bool
+    {
+        chosen.extend_from_slice(positions);
+        let more = self.report(chosen, found);
+        chosen.truncate(chosen.len() - positions.len());
+        more
     }
 
+    /// Convert positions in the sorted list back to indexes in the original list
+    fn report<F>(&self, chosen: &[usize], found: &mut F) -> bool where F: FnMut(&[usize]) -> bool {
+        let mut indexes: Vec<usize> = chosen.iter()
+            .map(|p| self.entries[*p].1)
+            .collect();
+        indexes.sort_unstable();
+        found(&indexes)
+    }
 }
 
-fn main() {
-    let permutations: usize = env::args()
-        .nth(1).expect("number of permutations missing")
-        .parse().expect("error parsing number of permutations");
+/// What to print once the combinations have been found
+enum Output {
+    /// product of the first combination found
+    First,
+    /// indexes, values and product of every combination
+    All,
+    /// number of combinations
+    Count
+}
 
-    let path = env::args()
-        .nth(2).expect("path to file missing");
+struct Options {
+    permutations: usize,
+    path: String,
+    target: String,
+    number_type: String,
+    output: Output
+}
 
-    let file = File::open(path).expect("cannot open file");
+impl Options {
+
+    fn parse() -> Options {
+        let mut target = "2020".to_string();
+        let mut number_type = "i64".to_string();
+        let mut output = Output::First;
+        let mut positional = Vec::new();
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--target" => target = args.next().expect("target missing"),
+                "--type" => number_type = args.next().expect("number type missing"),
+                "--all" => output = Output::All,
+                "--count" => output = Output::Count,
+                _ => positional.push(arg)
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let permutations = positional
+            .next().expect("number of permutations missing")
+            .parse().expect("error parsing number of permutations");
+
+        let path = positional
+            .next().expect("path to file missing");
+
+        Options { permutations, path, target, number_type, output }
+    }
+}
+
+fn product<T: Number>(numbers: &[T], indexes: &[usize]) -> Result<T, Error<T>> {
+    let values: Vec<T> = indexes.iter().map(|i| numbers[*i]).collect();
+    values.iter()
+        .try_fold(T::ONE, |total, value| total.checked_mul(*value))
+        .ok_or_else(|| Error::Product { indexes: indexes.to_vec(), values })
+}
+
+fn run<T: Number>(options: &Options) -> Result<(), Error<T>> where T::Err: Debug {
+    let target: T = options.target.parse().expect("error parsing target");
+
+    let file = File::open(&options.path).expect("cannot open file");
     let reader = BufReader::new(file);
 
-    let numbers: Vec<u32> = reader.lines()
+    let numbers: Vec<T> = reader.lines()
         .map(|result| result
             .expect("error reading line")
             .parse()
@@ -112,18 +461,168 @@ fn main() {
         )
         .collect();
 
-    let permutations = Permutation::new(permutations, numbers.len());
-    
-    let total: u32 = permutations
-        .map(|indexes| {
-            indexes.iter().map(|i| numbers[*i]).collect::<Vec<u32>>()
-        })
-        .filter(|selected| {
-            selected.iter().sum::<u32>() == 2020
-        })
-        .map(|selected| selected.iter().product())
-        .next()
-        .expect("no numbers found that sum to 2020");
+    let solver = KSum::new(&numbers);
+
+    match options.output {
+        Output::First => {
+            let mut selected = None;
+            solver.search(options.permutations, target, |indexes| {
+                selected = Some(indexes.to_vec());
+                false
+            })?;
+
+            let selected = selected
+                .unwrap_or_else(|| panic!("no numbers found that sum to {}", target));
 
-    println!("{}", total);
-}
\ No newline at end of file
+            println!("{}", product(&numbers, &selected)?);
+        },
+        Output::All => {
+            let mut result = Ok(());
+            solver.search(options.permutations, target, |indexes| {
+                result = product(&numbers, indexes).map(|total| {
+                    let values: Vec<T> = indexes.iter().map(|i| numbers[*i]).collect();
+                    println!("{:?} {:?} {}", indexes, values, total);
+                });
+                result.is_ok()
+            })?;
+            result?;
+        },
+        Output::Count => {
+            let mut count = 0;
+            solver.search(options.permutations, target, |_| {
+                count += 1;
+                true
+            })?;
+            println!("{}", count);
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let options = Options::parse();
+
+    let result = match options.number_type.as_str() {
+        "u64" => run::<u64>(&options).map_err(|e| e.to_string()),
+        "i64" => run::<i64>(&options).map_err(|e| e.to_string()),
+        "u128" => run::<u128>(&options).map_err(|e| e.to_string()),
+        _ => panic!("invalid number type")
+    };
+
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use xorshift::XorShift;
+
+    fn count<T: Number>(numbers: &[T], size: usize, target: T) -> Result<usize, Error<T>> {
+        let mut count = 0;
+        KSum::new(numbers).search(size, target, |_| {
+            count += 1;
+            true
+        })?;
+        Ok(count)
+    }
+
+    #[test]
+    fn finds_the_puzzle_answer() {
+        let numbers: Vec<i64> = vec![1721, 979, 366, 299, 675, 1456];
+        let mut selected = Vec::new();
+        KSum::new(&numbers).search(3, 2020, |indexes| {
+            selected = indexes.to_vec();
+            false
+        }).unwrap();
+        assert_eq!(selected, vec![1, 2, 4]);
+        assert_eq!(product(&numbers, &selected), Ok(241861950));
+    }
+
+    #[test]
+    fn accepts_negative_entries() {
+        let numbers: Vec<i64> = vec![-5, 10, 3, -2, 7];
+        assert_eq!(count(&numbers, 2, 5), Ok(2));
+        assert_eq!(count(&numbers, 3, 0), Ok(1));
+        assert_eq!(count(&numbers, 4, 6), Ok(1));
+    }
+
+    #[test]
+    fn large_64_bit_entries_do_not_overflow() {
+        let numbers = vec![i64::MAX, i64::MAX, i64::MIN, 1];
+        assert_eq!(count(&numbers, 3, i64::MAX - 1), Ok(1));
+        assert_eq!(count(&[u64::MAX; 5], 5, 1), Ok(0));
+    }
+
+    #[test]
+    fn u128_entries_above_i128_are_searched() {
+        let big = u128::MAX - 10;
+        let numbers = vec![big, 4, 6, 2];
+        assert_eq!(count(&numbers, 2, big + 4), Ok(1));
+        assert_eq!(count(&[i128::MAX as u128, 1], 2, 5), Ok(0));
+    }
+
+    #[test]
+    fn overflow_names_the_evaluated_combination() {
+        let numbers = vec![3, u128::MAX, 1];
+        assert_eq!(
+            count(&numbers, 2, 5),
+            Err(Error::Sum { indexes: vec![1, 2], values: vec![u128::MAX, 1] })
+        );
+    }
+
+    #[test]
+    fn product_overflow_is_reported() {
+        let numbers = vec![u64::MAX, 2];
+        assert_eq!(
+            product(&numbers, &[0, 1]),
+            Err(Error::Product { indexes: vec![0, 1], values: vec![u64::MAX, 2] })
+        );
+    }
+
+    fn entries(count: usize, limit: u64, generator: &mut XorShift) -> Vec<i64> {
+        (0..count).map(|_| generator.below(limit) as i64 - limit as i64 / 2).collect()
+    }
+
+    #[test]
+    fn quads_match_brute_force() {
+        let mut generator = XorShift::default();
+        for _ in 0..20 {
+            let numbers = entries(24, 40, &mut generator);
+            for target in -20..20 {
+                let mut expected = 0;
+                for a in 0..numbers.len() {
+                    for b in a + 1..numbers.len() {
+                        for c in b + 1..numbers.len() {
+                            for d in c + 1..numbers.len() {
+                                if numbers[a] + numbers[b] + numbers[c] + numbers[d] == target {
+                                    expected += 1;
+                                }
+                            }
+                        }
+                    }
+                }
+                assert_eq!(count(&numbers, 4, target), Ok(expected), "{:?} {}", numbers, target);
+            }
+        }
+    }
+
+    /// Thousands of entries where no group of four reaches the target, so that every pair is looked at
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "timed, run with cargo test --release")]
+    fn quads_on_thousands_of_entries() {
+        let numbers: Vec<u64> = {
+            let mut generator = XorShift::default();
+            (0..5000).map(|_| generator.below(1_000_000) * 2).collect()
+        };
+
+        let start = Instant::now();
+        assert_eq!(count(&numbers, 4, 2_000_001), Ok(0));
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_millis(500), "took {:?}", elapsed);
+    }
+}
@@ -1,103 +1,387 @@
 use std::env;
+use std::fmt;
+use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::process;
+
+mod policy;
+mod text;
+
+use policy::{Policy, Registry};
+use text::{Form, Normalization, Unit};
 
 #[derive(Debug)]
 struct Entry {
+    line: usize,
     min: usize,
     max: usize,
-    letter: char,
-    password: String
+    letter: String,
+    password: String,
+    unit: Unit
 }
 
-enum Mode {
-    Count,
-    Position
+/// Why a line could not be parsed into an entry
+#[derive(Debug, PartialEq)]
+enum ParseErrorKind {
+    MissingRange,
+    BadBound(String),
+    MinAboveMax,
+    ZeroPosition,
+    MissingLetter,
+    BadLetter(String),
+    LongLetter(String, Unit),
+    MissingPassword
 }
 
-impl Mode {
+#[derive(Debug)]
+struct ParseError {
+    line: usize,
+    column: usize,
+    kind: ParseErrorKind
+}
 
-    fn is_valid(&self, entry: &Entry) -> bool {
-        match self {
-            Mode::Count => Self::validate_count(entry),
-            Mode::Position => Self::validate_position(entry),
+/// How to output the validation of each entry
+enum Report {
+    Text,
+    Json
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{} {}: {}", self.min, self.max, self.letter, self.password)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::MissingRange => write!(f, "range not found"),
+            ParseErrorKind::BadBound(text) => write!(f, "cannot parse bound {:?}", text),
+            ParseErrorKind::MinAboveMax => write!(f, "min is greater than max"),
+            ParseErrorKind::ZeroPosition => write!(f, "positions start at 1"),
+            ParseErrorKind::MissingLetter => write!(f, "letter not found"),
+            ParseErrorKind::BadLetter(text) => write!(f, "cannot parse letter {:?}", text),
+            ParseErrorKind::LongLetter(letter, unit) => write!(f, "letter {:?} is more than one {}", letter, unit),
+            ParseErrorKind::MissingPassword => write!(f, "password not found")
         }
     }
+}
+
+impl Entry {
+
+    /// Parse a line like `1-3 a: abcde`. Positions can't be 0 when validating with a positional policy.
+    /// The letter and password are normalized, and the letter must be written as a single unit.
+    fn parse(line: usize, text: &str, policy: &dyn Policy, unit: Unit, normalization: &Normalization)
+        -> Result<Entry, ParseError>
+    {
+        // columns are counted in characters, starting at 1
+        let error = |offset: usize, kind| ParseError {
+            line,
+            column: text[..offset].chars().count() + 1,
+            kind
+        };
+
+        let range_end = text.find(' ').unwrap_or(text.len());
+        let dash = text[..range_end].find('-')
+            .ok_or_else(|| error(0, ParseErrorKind::MissingRange))?;
 
-    fn validate_count(entry: &Entry) -> bool {
-        let count = entry.password.chars()
-        .filter(|c| c == &entry.letter)
-        .count();
-        count >= entry.min && count <= entry.max
+        let bound = |start: usize, end: usize| -> Result<usize, ParseError> {
+            let digits = &text[start..end];
+            let value = digits.parse()
+                .map_err(|_| error(start, ParseErrorKind::BadBound(digits.to_string())))?;
+            if value == 0 && policy.positional() {
+                return Err(error(start, ParseErrorKind::ZeroPosition));
+            }
+            Ok(value)
+        };
+
+        let min = bound(0, dash)?;
+        let max = bound(dash + 1, range_end)?;
+        if min > max {
+            return Err(error(0, ParseErrorKind::MinAboveMax));
+        }
+
+        let letter_start = (range_end + 1).min(text.len());
+        let letter_end = text[letter_start..].find(' ')
+            .map(|i| letter_start + i)
+            .unwrap_or(text.len());
+
+        let token = &text[letter_start..letter_end];
+        if token.is_empty() {
+            return Err(error(letter_start, ParseErrorKind::MissingLetter));
+        }
+
+        let letter = match token.strip_suffix(':') {
+            Some(letter) if !letter.is_empty() => letter,
+            _ => return Err(error(letter_start, ParseErrorKind::BadLetter(token.to_string())))
+        };
+        // folding can turn a single letter into several, like `ß` into `ss`, so the letter
+        // is measured before case is folded
+        let written = Normalization { fold_case: false, ..*normalization }.apply(letter);
+        if unit.split(&written).len() != 1 {
+            return Err(error(letter_start, ParseErrorKind::LongLetter(written, unit)));
+        }
+        let letter = normalization.apply(letter);
+
+        let password_start = (letter_end + 1).min(text.len());
+        let password = text[password_start..].split(' ')
+            .next()
+            .unwrap_or("");
+        if password.is_empty() {
+            return Err(error(password_start, ParseErrorKind::MissingPassword));
+        }
+
+        Ok(Entry {
+            line,
+            min,
+            max,
+            letter,
+            password: normalization.apply(password),
+            unit
+        })
     }
 
-    fn validate_position(entry: &Entry) -> bool {
-        let count = [entry.min, entry.max]
-            .iter()
-            .filter(|pos| {
-                let letter = entry.password.chars().nth(*pos - 1).expect("cannot find letter");
-                letter == entry.letter
-            })
-            .count();
-        count == 1
+    /// The password cut into units
+    fn units(&self) -> Vec<&[u8]> {
+        self.unit.split(&self.password)
     }
+
+    /// The letter cut into units. It is a single unit, unless folding case made it longer.
+    fn letter_units(&self) -> Vec<&[u8]> {
+        self.unit.split(&self.letter)
+    }
+
 }
 
-impl Entry {
+/// Escape a string so that it can be embedded in a JSON document
+fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+impl Report {
 
-    fn parse(line: String) -> Entry {
-        let mut parts = line.split(" ");
-
-        let range = parts.next().expect("range not found");
-
-        let letter = parts.next()
-            .expect("letter not found")
-            .trim_end_matches(":")
-            .parse()
-            .expect("cannot parse letter");
-
-        let password = parts.next().expect("password not found");
-
-        let mut range = range.split("-");
-        let min: usize = range.next()
-            .expect("min not found")
-            .parse()
-            .expect("cannot parse min");
-        let max: usize = range.next()
-            .expect("max not found")
-            .parse()
-            .expect("cannot parse max");
-
-        Entry {
-            min: min,
-            max: max,
-            letter: letter,
-            password: password.to_string()
+    fn print(&self, policy: &dyn Policy, entries: &[Entry]) {
+        match self {
+            Report::Text => Self::print_text(policy, entries),
+            Report::Json => Self::print_json(policy, entries)
         }
     }
 
+    fn print_text(policy: &dyn Policy, entries: &[Entry]) {
+        for entry in entries {
+            match policy.check(entry) {
+                Ok(()) => println!("{}: {} valid", entry.line, entry),
+                Err(failure) => println!("{}: {} invalid, {}", entry.line, entry, failure.describe(entry))
+            }
+        }
+    }
+
+    fn print_json(policy: &dyn Policy, entries: &[Entry]) {
+        println!("[");
+        for (i, entry) in entries.iter().enumerate() {
+            let result = match policy.check(entry) {
+                Ok(()) => "\"valid\": true".to_string(),
+                Err(failure) => format!(
+                    "\"valid\": false, \"failure\": {}, \"reason\": {}",
+                    json_string(failure.kind()),
+                    json_string(&failure.describe(entry))
+                )
+            };
+            let separator = if i + 1 < entries.len() { "," } else { "" };
+            println!(
+                "  {{\"line\": {}, \"min\": {}, \"max\": {}, \"letter\": {}, \"password\": {}, {}}}{}",
+                entry.line,
+                entry.min,
+                entry.max,
+                json_string(&entry.letter),
+                json_string(&entry.password),
+                result,
+                separator
+            );
+        }
+        println!("]");
+    }
 }
 
 fn main() {
-    let mode = env::args().nth(1).expect("missing mode");
-    let mode = match mode.as_str() {
-        "count" => Mode::Count,
-        "position" => Mode::Position,
-        _ => panic!("invalid mode")
-    };
-
-    let path = env::args().nth(2).expect("missing path to file");
+    let mut policy = None;
+    let mut report = None;
+    let mut lenient = false;
+    let mut unit = Unit::Char;
+    let mut normalization = Normalization::default();
+    let mut positional = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--policy-file" => {
+                let path = args.next().expect("missing path to policy file");
+                let text = fs::read_to_string(path).expect("cannot read policy file");
+                policy = Some(text);
+            },
+            "--report" => report = Some(Report::Text),
+            "--json" => report = Some(Report::Json),
+            "--lenient" => lenient = true,
+            "--unit" => {
+                let name = args.next().expect("missing unit");
+                unit = Unit::parse(&name).expect("invalid unit");
+            },
+            "--normalize" => {
+                let name = args.next().expect("missing normalization form");
+                normalization.form = Some(Form::parse(&name).expect("invalid normalization form"));
+            },
+            "--fold-case" => normalization.fold_case = true,
+            _ => positional.push(arg)
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let policy = policy
+        .or_else(|| positional.next())
+        .expect("missing policy");
+
+    let policy = Registry::new(normalization)
+        .parse(&policy)
+        .unwrap_or_else(|e| {
+            eprintln!("invalid policy: {}", e);
+            process::exit(1);
+        });
+
+    let path = positional.next().expect("missing path to file");
     let file = File::open(path).expect("cannot open file");
     let buffer = BufReader::new(file);
 
-    let entries: Vec<Entry> = buffer.lines()
-        .map(|l| Entry::parse(l.expect("cannot read line")))
-        .collect();
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in buffer.lines().enumerate() {
+        let line = line.expect("cannot read line");
+        match Entry::parse(i + 1, &line, policy.as_ref(), unit, &normalization) {
+            Ok(entry) => entries.push(entry),
+            Err(error) if lenient => errors.push(error),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+    }
+
+    match report {
+        Some(report) => report.print(policy.as_ref(), &entries),
+        None => {
+            let valid = entries.iter()
+                .filter(|e| policy.check(e).is_ok())
+                .count();
+
+            println!("{}", valid)
+        }
+    }
+
+    if !errors.is_empty() {
+        eprintln!("skipped {} invalid lines", errors.len());
+        for error in errors {
+            eprintln!("{}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let valid = entries.iter()
-        .filter(|e| mode.is_valid(&e))
-        .count();
-        
-    println!("{}", valid)
+    /// Line, column and kind of the error found when parsing the text as line 42
+    fn error(text: &str, policy: &str) -> (usize, usize, ParseErrorKind) {
+        let policy = Registry::new(Normalization::default()).parse(policy).unwrap();
+        let error = Entry::parse(42, text, policy.as_ref(), Unit::Char, &Normalization::default())
+            .expect_err("entry should not parse");
+        (error.line, error.column, error.kind)
+    }
+
+    #[test]
+    fn parses_entries() {
+        let policy = Registry::new(Normalization::default()).parse("count").unwrap();
+        let entry = Entry::parse(1, "1-3 a: abcde", policy.as_ref(), Unit::Char, &Normalization::default()).unwrap();
+        assert_eq!((entry.min, entry.max, entry.letter.as_str(), entry.password.as_str()), (1, 3, "a", "abcde"));
+        assert_eq!(entry.to_string(), "1-3 a: abcde");
+    }
+
+    #[test]
+    fn missing_range() {
+        assert_eq!(error("abc", "count"), (42, 1, ParseErrorKind::MissingRange));
+        assert_eq!(error("13 a: abc", "count"), (42, 1, ParseErrorKind::MissingRange));
+    }
+
+    #[test]
+    fn bad_bound() {
+        assert_eq!(error("x-3 a: abc", "count"), (42, 1, ParseErrorKind::BadBound("x".to_string())));
+        assert_eq!(error("1-y a: abc", "count"), (42, 3, ParseErrorKind::BadBound("y".to_string())));
+        assert_eq!(error("1- a: abc", "count"), (42, 3, ParseErrorKind::BadBound("".to_string())));
+        assert_eq!(error("-1-3 a: abc", "count"), (42, 1, ParseErrorKind::BadBound("".to_string())));
+    }
+
+    #[test]
+    fn min_above_max() {
+        assert_eq!(error("3-1 a: abc", "count"), (42, 1, ParseErrorKind::MinAboveMax));
+    }
+
+    #[test]
+    fn zero_position() {
+        assert_eq!(error("0-2 a: abc", "position"), (42, 1, ParseErrorKind::ZeroPosition));
+        assert_eq!(error("1-0 a: abc", "position"), (42, 3, ParseErrorKind::ZeroPosition));
+        let policy = Registry::new(Normalization::default()).parse("count").unwrap();
+        assert!(Entry::parse(1, "0-2 a: abc", policy.as_ref(), Unit::Char, &Normalization::default()).is_ok());
+    }
+
+    #[test]
+    fn missing_letter() {
+        assert_eq!(error("1-3", "count"), (42, 4, ParseErrorKind::MissingLetter));
+        assert_eq!(error("1-3  abc", "count"), (42, 5, ParseErrorKind::MissingLetter));
+    }
+
+    #[test]
+    fn bad_letter() {
+        assert_eq!(error("1-3 a abc", "count"), (42, 5, ParseErrorKind::BadLetter("a".to_string())));
+        assert_eq!(error("1-3 : abc", "count"), (42, 5, ParseErrorKind::BadLetter(":".to_string())));
+    }
+
+    #[test]
+    fn long_letter() {
+        assert_eq!(error("1-3 ab: abc", "count"), (42, 5, ParseErrorKind::LongLetter("ab".to_string(), Unit::Char)));
+        assert_eq!(error("1-3 e\u{301}: abc", "count"),
+                   (42, 5, ParseErrorKind::LongLetter("e\u{301}".to_string(), Unit::Char)));
+        assert_eq!(error("1-3 🇫🇷: abc", "count"), (42, 5, ParseErrorKind::LongLetter("🇫🇷".to_string(), Unit::Char)));
+
+        let policy = Registry::new(Normalization::default()).parse("count").unwrap();
+        let entry = Entry::parse(1, "1-3 e\u{301}: abc", policy.as_ref(), Unit::Grapheme, &Normalization::default());
+        assert_eq!(entry.unwrap().letter, "e\u{301}");
+    }
+
+    #[test]
+    fn missing_password() {
+        assert_eq!(error("1-3 a:", "count"), (42, 7, ParseErrorKind::MissingPassword));
+        assert_eq!(error("1-3 a: ", "count"), (42, 8, ParseErrorKind::MissingPassword));
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        assert_eq!(error("1-3 é:", "count"), (42, 7, ParseErrorKind::MissingPassword));
+        assert_eq!(error("12-13 é:", "count"), (42, 9, ParseErrorKind::MissingPassword));
+        assert_eq!(error("é-3 a: abc", "count"), (42, 1, ParseErrorKind::BadBound("é".to_string())));
+        assert_eq!(error("1-3 é é: abc", "count"), (42, 5, ParseErrorKind::BadLetter("é".to_string())));
+    }
 }
@@ -0,0 +1,500 @@
+use std::collections::{HashMap, HashSet};
+
+use expr::Expr;
+use regex::Regex;
+
+use crate::Entry;
+use crate::text::Normalization;
+
+/// A rule that decides if the password of an entry is valid
+pub trait Policy {
+
+    fn check(&self, entry: &Entry) -> Result<(), Failure>;
+
+    /// Positional policies treat the bounds of an entry as positions in the password,
+    /// which start at 1
+    fn positional(&self) -> bool {
+        false
+    }
+}
+
+/// Why an entry was rejected by a policy
+#[derive(Debug)]
+pub enum Failure {
+    TooFew { count: usize },
+    TooMany { count: usize },
+    NeitherPosition,
+    BothPositions,
+    OutOfRange { position: usize },
+    Forbidden { letter: String },
+    TooFewDistinct { count: usize, min: usize },
+    NoMatch { pattern: String },
+    Negated { policy: String },
+    NoneMatched(Vec<Failure>)
+}
+
+impl Failure {
+
+    /// Short identifier used in machine readable reports
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Failure::TooFew { .. } => "too_few",
+            Failure::TooMany { .. } => "too_many",
+            Failure::NeitherPosition => "neither_position",
+            Failure::BothPositions => "both_positions",
+            Failure::OutOfRange { .. } => "out_of_range",
+            Failure::Forbidden { .. } => "forbidden",
+            Failure::TooFewDistinct { .. } => "too_few_distinct",
+            Failure::NoMatch { .. } => "no_match",
+            Failure::Negated { .. } => "negated",
+            Failure::NoneMatched(_) => "none_matched"
+        }
+    }
+
+    pub fn describe(&self, entry: &Entry) -> String {
+        match self {
+            Failure::TooFew { count } =>
+                format!("letter {} appears {} times, expected at least {}", entry.letter, count, entry.min),
+            Failure::TooMany { count } =>
+                format!("letter {} appears {} times, expected at most {}", entry.letter, count, entry.max),
+            Failure::NeitherPosition =>
+                format!("letter {} is at neither position {} nor {}", entry.letter, entry.min, entry.max),
+            Failure::BothPositions =>
+                format!("letter {} is at both positions {} and {}", entry.letter, entry.min, entry.max),
+            Failure::OutOfRange { position } =>
+                format!("position {} is outside of password", position),
+            Failure::Forbidden { letter } =>
+                format!("letter {} is forbidden", letter),
+            Failure::TooFewDistinct { count, min } =>
+                format!("password has {} distinct {}s, expected at least {}", count, entry.unit, min),
+            Failure::NoMatch { pattern } =>
+                format!("password does not match {}", pattern),
+            Failure::Negated { policy } =>
+                format!("password is accepted by {}", policy),
+            Failure::NoneMatched(failures) => failures.iter()
+                .map(|f| f.describe(entry))
+                .collect::<Vec<String>>()
+                .join(" and ")
+        }
+    }
+}
+
+/// The letter of the entry must appear between min and max times
+struct Count;
+
+/// The letter of the entry must be at exactly one of the positions min and max
+struct Position;
+
+/// None of these letters may appear in the password. Without letters, the letter of the entry is forbidden.
+struct Forbidden(Option<String>);
+
+/// The password must use at least this many different units
+struct Distinct(usize);
+
+struct Match(Regex);
+
+struct All(Vec<Box<dyn Policy>>);
+
+struct Any(Vec<Box<dyn Policy>>);
+
+struct Not {
+    policy: Box<dyn Policy>,
+    text: String
+}
+
+impl Policy for Count {
+
+    fn check(&self, entry: &Entry) -> Result<(), Failure> {
+        let units = entry.units();
+        let letter = entry.letter_units();
+
+        // letters made of several units are counted without overlapping
+        let mut count = 0;
+        let mut position = 0;
+        while position < units.len() {
+            match units[position..].starts_with(&letter) {
+                true => {
+                    count += 1;
+                    position += letter.len();
+                },
+                false => position += 1
+            }
+        }
+
+        if count < entry.min {
+            Err(Failure::TooFew { count })
+        } else if count > entry.max {
+            Err(Failure::TooMany { count })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Policy for Position {
+
+    fn check(&self, entry: &Entry) -> Result<(), Failure> {
+        let units = entry.units();
+        let letter = entry.letter_units();
+        let mut count = 0;
+        for position in [entry.min, entry.max].iter() {
+            let start = position.checked_sub(1)
+                .filter(|p| *p < units.len())
+                .ok_or(Failure::OutOfRange { position: *position })?;
+            if units[start..].starts_with(&letter) {
+                count += 1;
+            }
+        }
+
+        match count {
+            0 => Err(Failure::NeitherPosition),
+            1 => Ok(()),
+            _ => Err(Failure::BothPositions)
+        }
+    }
+
+    fn positional(&self) -> bool {
+        true
+    }
+}
+
+impl Policy for Forbidden {
+
+    fn check(&self, entry: &Entry) -> Result<(), Failure> {
+        let letters = match &self.0 {
+            Some(letters) => entry.unit.split(letters),
+            None => {
+                let letter = entry.letter_units();
+                return match entry.units().windows(letter.len()).any(|units| units == letter) {
+                    true => Err(Failure::Forbidden { letter: entry.letter.clone() }),
+                    false => Ok(())
+                };
+            }
+        };
+
+        match entry.units().into_iter().find(|u| letters.contains(u)) {
+            Some(letter) => Err(Failure::Forbidden { letter: String::from_utf8_lossy(letter).to_string() }),
+            None => Ok(())
+        }
+    }
+}
+
+impl Policy for Distinct {
+
+    fn check(&self, entry: &Entry) -> Result<(), Failure> {
+        let count = entry.units()
+            .into_iter()
+            .collect::<HashSet<&[u8]>>()
+            .len();
+
+        match count >= self.0 {
+            true => Ok(()),
+            false => Err(Failure::TooFewDistinct { count, min: self.0 })
+        }
+    }
+}
+
+impl Policy for Match {
+
+    fn check(&self, entry: &Entry) -> Result<(), Failure> {
+        match self.0.is_match(&entry.password) {
+            true => Ok(()),
+            false => Err(Failure::NoMatch { pattern: self.0.as_str().to_string() })
+        }
+    }
+}
+
+impl Policy for All {
+
+    fn check(&self, entry: &Entry) -> Result<(), Failure> {
+        self.0.iter().try_for_each(|p| p.check(entry))
+    }
+
+    fn positional(&self) -> bool {
+        self.0.iter().any(|p| p.positional())
+    }
+}
+
+impl Policy for Any {
+
+    fn check(&self, entry: &Entry) -> Result<(), Failure> {
+        let mut failures = Vec::new();
+        for policy in &self.0 {
+            match policy.check(entry) {
+                Ok(()) => return Ok(()),
+                Err(failure) => failures.push(failure)
+            }
+        }
+        Err(Failure::NoneMatched(failures))
+    }
+
+    fn positional(&self) -> bool {
+        self.0.iter().any(|p| p.positional())
+    }
+}
+
+impl Policy for Not {
+
+    fn check(&self, entry: &Entry) -> Result<(), Failure> {
+        match self.policy.check(entry) {
+            Ok(()) => Err(Failure::Negated { policy: self.text.clone() }),
+            Err(_) => Ok(())
+        }
+    }
+
+    fn positional(&self) -> bool {
+        self.policy.positional()
+    }
+}
+
+type Constructor = fn(&Registry, &[Expr]) -> Result<Box<dyn Policy>, String>;
+
+/// Maps the names used in policy expressions to the policies they build
+pub struct Registry {
+    constructors: HashMap<&'static str, Constructor>,
+    /// applied to letters given as arguments, so that they compare with normalized passwords
+    normalization: Normalization
+}
+
+impl Registry {
+
+    pub fn new(normalization: Normalization) -> Registry {
+        let mut registry = Registry {
+            constructors: HashMap::new(),
+            normalization
+        };
+        registry.register("count", |_, args| {
+            no_args("count", args)?;
+            Ok(Box::new(Count))
+        });
+        registry.register("position", |_, args| {
+            no_args("position", args)?;
+            Ok(Box::new(Position))
+        });
+        registry.register("forbidden", |registry, args| match args {
+            [] => Ok(Box::new(Forbidden(None))),
+            [Expr::Text(letters)] => Ok(Box::new(Forbidden(Some(registry.normalization.apply(letters))))),
+            _ => Err("forbidden takes an optional string of letters".to_string())
+        });
+        registry.register("distinct", |_, args| match args {
+            [Expr::Number(min)] => Ok(Box::new(Distinct(*min))),
+            _ => Err("distinct takes a number".to_string())
+        });
+        registry.register("regex", |_, args| match args {
+            [Expr::Text(pattern)] => Regex::new(pattern)
+                .map(|r| Box::new(Match(r)) as Box<dyn Policy>)
+                .map_err(|e| e.to_string()),
+            _ => Err("regex takes a pattern string".to_string())
+        });
+        registry.register("all", |registry, args| {
+            Ok(Box::new(All(registry.build_all(args)?)))
+        });
+        registry.register("any", |registry, args| {
+            Ok(Box::new(Any(registry.build_all(args)?)))
+        });
+        registry.register("not", |registry, args| match args {
+            [expr] => Ok(Box::new(Not {
+                policy: registry.build(expr)?,
+                text: expr.to_string()
+            })),
+            _ => Err("not takes a single policy".to_string())
+        });
+        registry
+    }
+
+    pub fn register(&mut self, name: &'static str, constructor: Constructor) {
+        self.constructors.insert(name, constructor);
+    }
+
+    pub fn build(&self, expr: &Expr) -> Result<Box<dyn Policy>, String> {
+        match expr {
+            Expr::Call(name, args) => {
+                let constructor = self.constructors.get(name.as_str())
+                    .ok_or_else(|| format!("unknown policy {}", name))?;
+                constructor(self, args)
+            },
+            expr => Err(format!("{} is not a policy", expr))
+        }
+    }
+
+    fn build_all(&self, exprs: &[Expr]) -> Result<Vec<Box<dyn Policy>>, String> {
+        exprs.iter().map(|e| self.build(e)).collect()
+    }
+
+    pub fn parse(&self, text: &str) -> Result<Box<dyn Policy>, String> {
+        self.build(&Expr::parse(text)?)
+    }
+}
+
+fn no_args(name: &str, args: &[Expr]) -> Result<(), String> {
+    match args.is_empty() {
+        true => Ok(()),
+        false => Err(format!("{} does not take arguments", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::{Form, Unit};
+
+    fn check(policy: &str, line: &str) -> Result<(), Failure> {
+        check_with(policy, line, Unit::Char, Normalization::default())
+    }
+
+    fn check_with(policy: &str, line: &str, unit: Unit, normalization: Normalization) -> Result<(), Failure> {
+        let policy = Registry::new(normalization).parse(policy).unwrap();
+        let entry = Entry::parse(1, line, policy.as_ref(), unit, &normalization).unwrap();
+        policy.check(&entry)
+    }
+
+    fn kind(policy: &str, line: &str) -> &'static str {
+        kind_with(policy, line, Unit::Char, Normalization::default())
+    }
+
+    fn kind_with(policy: &str, line: &str, unit: Unit, normalization: Normalization) -> &'static str {
+        match check_with(policy, line, unit, normalization) {
+            Ok(()) => "valid",
+            Err(failure) => failure.kind()
+        }
+    }
+
+    #[test]
+    fn keeps_backslashes_in_patterns() {
+        assert_eq!(kind(r#"regex("^\d+$")"#, "1-3 a: 123"), "valid");
+        assert_eq!(kind(r#"regex("^\d+$")"#, "1-3 a: ddd"), "no_match");
+    }
+
+    #[test]
+    fn rejects_unknown_policies_and_bad_arguments() {
+        let registry = Registry::new(Normalization::default());
+        assert!(registry.parse("length(3)").is_err());
+        assert!(registry.parse("count(1)").is_err());
+        assert!(registry.parse("distinct").is_err());
+        assert!(registry.parse("regex(\"(\")").is_err());
+        assert!(registry.parse("not(count, position)").is_err());
+        assert!(registry.parse("\"count\"").is_err());
+    }
+
+    #[test]
+    fn count() {
+        assert_eq!(kind("count", "1-3 a: abcde"), "valid");
+        assert_eq!(kind("count", "1-3 b: cdefg"), "too_few");
+        assert_eq!(kind("count", "1-2 c: ccccc"), "too_many");
+    }
+
+    #[test]
+    fn position() {
+        assert_eq!(kind("position", "1-3 a: abcde"), "valid");
+        assert_eq!(kind("position", "1-3 b: cdefg"), "neither_position");
+        assert_eq!(kind("position", "2-9 c: ccccccccc"), "both_positions");
+        assert_eq!(kind("position", "1-9 a: abc"), "out_of_range");
+    }
+
+    #[test]
+    fn forbidden() {
+        assert_eq!(kind("forbidden", "1-3 a: bcd"), "valid");
+        assert_eq!(kind("forbidden", "1-3 a: bad"), "forbidden");
+        assert_eq!(kind("forbidden(\"xyz\")", "1-3 a: abc"), "valid");
+        match check("forbidden(\"xyz\")", "1-3 a: abyc") {
+            Err(Failure::Forbidden { letter }) => assert_eq!(letter, "y"),
+            result => panic!("unexpected result {:?}", result)
+        }
+    }
+
+    #[test]
+    fn distinct() {
+        assert_eq!(kind("distinct(3)", "1-3 a: abca"), "valid");
+        assert_eq!(kind("distinct(3)", "1-3 a: abab"), "too_few_distinct");
+    }
+
+    #[test]
+    fn regex() {
+        assert_eq!(kind("regex(\"^[a-z]+$\")", "1-3 a: abc"), "valid");
+        assert_eq!(kind("regex(\"^[a-z]+$\")", "1-3 a: abc1"), "no_match");
+    }
+
+    #[test]
+    fn all() {
+        assert_eq!(kind("all(count, distinct(2))", "1-3 a: abc"), "valid");
+        assert_eq!(kind("all(count, distinct(4))", "1-3 a: abc"), "too_few_distinct");
+        assert_eq!(kind("all()", "1-3 a: abc"), "valid");
+    }
+
+    #[test]
+    fn any() {
+        assert_eq!(kind("any(position, count)", "1-3 a: aba"), "valid");
+        assert_eq!(kind("any(position, distinct(5))", "1-3 b: aaa"), "none_matched");
+        assert_eq!(kind("any()", "1-3 a: abc"), "none_matched");
+    }
+
+    #[test]
+    fn not() {
+        assert_eq!(kind("not(forbidden)", "1-3 a: abc"), "valid");
+        assert_eq!(kind("not(count)", "1-3 a: abc"), "negated");
+    }
+
+    #[test]
+    fn counts_and_positions_per_unit() {
+        let none = Normalization::default();
+        for unit in [Unit::Char, Unit::Grapheme] {
+            assert_eq!(kind_with("count", "1-2 é: café", unit, none), "valid");
+            assert_eq!(kind_with("count", "2-3 é: café", unit, none), "too_few");
+            assert_eq!(kind_with("count", "1-1 é: été", unit, none), "too_many");
+            assert_eq!(kind_with("position", "1-1 é: café", unit, none), "neither_position");
+            assert_eq!(kind_with("position", "1-4 é: café", unit, none), "valid");
+            assert_eq!(kind_with("position", "1-3 é: été", unit, none), "both_positions");
+        }
+
+        // a flag is two chars but a single grapheme
+        assert_eq!(kind_with("count", "2-3 🇫🇷: a🇫🇷🇫🇷", Unit::Grapheme, none), "valid");
+        assert_eq!(kind_with("position", "2-3 🇫🇷: a🇫🇷🇫🇷", Unit::Grapheme, none), "both_positions");
+        assert_eq!(kind_with("position", "1-4 🇫🇷: a🇫🇷🇫🇷", Unit::Grapheme, none), "out_of_range");
+
+        // bytes split letters outside of ASCII, so only ASCII letters can be checked
+        assert_eq!(kind_with("count", "1-2 a: été", Unit::Byte, none), "too_few");
+        assert_eq!(kind_with("position", "3-4 t: été", Unit::Byte, none), "valid");
+    }
+
+    #[test]
+    fn normalized_forms_compare_equal() {
+        let composed = "1-1 \u{e9}: caf\u{e9}";
+        let decomposed = "1-1 \u{e9}: cafe\u{301}";
+        for form in [Form::Nfc, Form::Nfd] {
+            let normalization = Normalization { form: Some(form), fold_case: false };
+            assert_eq!(kind_with("count", composed, Unit::Grapheme, normalization), "valid");
+            assert_eq!(kind_with("count", decomposed, Unit::Grapheme, normalization), "valid");
+            assert_eq!(kind_with("position", "1-4 \u{e9}: cafe\u{301}", Unit::Grapheme, normalization), "valid");
+        }
+        assert_eq!(kind_with("count", decomposed, Unit::Grapheme, Normalization::default()), "too_few");
+    }
+
+    #[test]
+    fn folded_letters_match_as_substrings() {
+        let folded = Normalization { form: None, fold_case: true };
+        for unit in [Unit::Char, Unit::Grapheme] {
+            assert_eq!(kind_with("count", "1-2 Ä: äÄ", unit, Normalization::default()), "valid");
+            assert_eq!(kind_with("count", "2-2 Ä: äÄ", unit, Normalization::default()), "too_few");
+            assert_eq!(kind_with("count", "2-2 Ä: äÄ", unit, folded), "valid");
+            assert_eq!(kind_with("position", "1-2 Ä: äÄ", unit, folded), "both_positions");
+            assert_eq!(kind_with("count", "1-1 ß: STRASSE", unit, folded), "valid");
+            assert_eq!(kind_with("count", "2-2 ß: sss", unit, folded), "too_few");
+            assert_eq!(kind_with("position", "5-6 ß: straße", unit, folded), "valid");
+            assert_eq!(kind_with("position", "4-6 ß: straße", unit, folded), "neither_position");
+            assert_eq!(kind_with("position", "1-8 ß: strasse", unit, folded), "out_of_range");
+            assert_eq!(kind_with("forbidden", "1-2 ß: Strasse", unit, folded), "forbidden");
+            assert_eq!(kind_with("forbidden", "1-2 ß: stase", unit, folded), "valid");
+        }
+    }
+
+    #[test]
+    fn positional_policies_reject_position_zero() {
+        let registry = Registry::new(Normalization::default());
+        let policy = registry.parse("any(count, not(position))").unwrap();
+        assert!(policy.positional());
+        assert!(Entry::parse(1, "0-3 a: abc", policy.as_ref(), Unit::Char, &Normalization::default()).is_err());
+
+        let policy = registry.parse("count").unwrap();
+        assert!(Entry::parse(1, "0-3 a: abc", policy.as_ref(), Unit::Char, &Normalization::default()).is_ok());
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+
+use caseless::default_case_fold_str;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// What a single letter or position of a password is made of
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Byte,
+    Char,
+    /// What a reader would see as a single letter, like `é` written as `e` followed by an accent
+    Grapheme
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Form {
+    Nfc,
+    Nfd
+}
+
+/// How passwords and letters are rewritten before being compared
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Normalization {
+    pub form: Option<Form>,
+    /// Full Unicode case folding, so that `ß` and `SS` both become `ss`.
+    /// A letter that folds into several units is matched as a run of units.
+    pub fold_case: bool
+}
+
+impl Unit {
+
+    pub fn parse(name: &str) -> Option<Unit> {
+        match name {
+            "byte" => Some(Unit::Byte),
+            "char" => Some(Unit::Char),
+            "grapheme" => Some(Unit::Grapheme),
+            _ => None
+        }
+    }
+
+    /// Cut the text into units. Units are compared as bytes so that all kinds can be handled the same way.
+    pub fn split<'a>(&self, text: &'a str) -> Vec<&'a [u8]> {
+        match self {
+            Unit::Byte => text.as_bytes().chunks(1).collect(),
+            Unit::Char => text.char_indices()
+                .map(|(i, c)| &text.as_bytes()[i..i + c.len_utf8()])
+                .collect(),
+            Unit::Grapheme => text.graphemes(true)
+                .map(|g| g.as_bytes())
+                .collect()
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unit::Byte => write!(f, "byte"),
+            Unit::Char => write!(f, "char"),
+            Unit::Grapheme => write!(f, "grapheme")
+        }
+    }
+}
+
+impl Form {
+
+    pub fn parse(name: &str) -> Option<Form> {
+        match name {
+            "nfc" => Some(Form::Nfc),
+            "nfd" => Some(Form::Nfd),
+            _ => None
+        }
+    }
+}
+
+impl Normalization {
+
+    /// Case is folded first, since folding can produce sequences that aren't normalized
+    pub fn apply(&self, text: &str) -> String {
+        let text = match self.fold_case {
+            true => default_case_fold_str(text),
+            false => text.to_string()
+        };
+
+        match self.form {
+            Some(Form::Nfc) => text.nfc().collect(),
+            Some(Form::Nfd) => text.nfd().collect(),
+            None => text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `é` as a single code point, then as `e` followed by a combining acute accent
+    const COMPOSED: &str = "caf\u{e9}";
+    const DECOMPOSED: &str = "cafe\u{301}";
+
+    fn normalization(form: Option<Form>, fold_case: bool) -> Normalization {
+        Normalization { form, fold_case }
+    }
+
+    #[test]
+    fn split_ascii() {
+        for unit in [Unit::Byte, Unit::Char, Unit::Grapheme] {
+            assert_eq!(unit.split("abc"), vec![b"a", b"b", b"c"], "{}", unit);
+        }
+    }
+
+    #[test]
+    fn split_composed() {
+        assert_eq!(Unit::Byte.split(COMPOSED).len(), 5);
+        assert_eq!(Unit::Char.split(COMPOSED).len(), 4);
+        assert_eq!(Unit::Grapheme.split(COMPOSED).len(), 4);
+        assert_eq!(Unit::Char.split(COMPOSED)[3], "\u{e9}".as_bytes());
+    }
+
+    #[test]
+    fn split_decomposed() {
+        assert_eq!(Unit::Byte.split(DECOMPOSED).len(), 6);
+        assert_eq!(Unit::Char.split(DECOMPOSED).len(), 5);
+        assert_eq!(Unit::Grapheme.split(DECOMPOSED).len(), 4);
+        assert_eq!(Unit::Grapheme.split(DECOMPOSED)[3], "e\u{301}".as_bytes());
+    }
+
+    #[test]
+    fn split_flags() {
+        // two regional indicator pairs, the French then the Japanese flag
+        let flags = "\u{1f1eb}\u{1f1f7}\u{1f1ef}\u{1f1f5}";
+        assert_eq!(Unit::Byte.split(flags).len(), 16);
+        assert_eq!(Unit::Char.split(flags).len(), 4);
+        assert_eq!(Unit::Grapheme.split(flags), vec!["\u{1f1eb}\u{1f1f7}".as_bytes(), "\u{1f1ef}\u{1f1f5}".as_bytes()]);
+    }
+
+    #[test]
+    fn split_empty() {
+        for unit in [Unit::Byte, Unit::Char, Unit::Grapheme] {
+            assert!(unit.split("").is_empty(), "{}", unit);
+        }
+    }
+
+    #[test]
+    fn forms_match_composed_and_decomposed() {
+        assert_ne!(COMPOSED, DECOMPOSED);
+        for form in [Form::Nfc, Form::Nfd] {
+            let normalization = normalization(Some(form), false);
+            assert_eq!(normalization.apply(COMPOSED), normalization.apply(DECOMPOSED));
+        }
+        assert_eq!(normalization(Some(Form::Nfc), false).apply(DECOMPOSED), COMPOSED);
+        assert_eq!(normalization(Some(Form::Nfd), false).apply(COMPOSED), DECOMPOSED);
+    }
+
+    #[test]
+    fn no_form_keeps_text() {
+        assert_eq!(Normalization::default().apply(DECOMPOSED), DECOMPOSED);
+        assert_eq!(Normalization::default().apply("ABC"), "ABC");
+    }
+
+    #[test]
+    fn fold_case() {
+        let fold = normalization(None, true);
+        assert_eq!(fold.apply("ABC"), "abc");
+        assert_eq!(fold.apply("Stra\u{df}e"), fold.apply("STRASSE"));
+        assert_eq!(fold.apply("\u{df}"), "ss");
+        // final and medial sigma fold to the same letter, which lowercasing keeps apart
+        assert_eq!(fold.apply("\u{3c2}"), fold.apply("\u{3a3}"));
+    }
+
+    #[test]
+    fn fold_case_then_normalize() {
+        let fold = normalization(Some(Form::Nfc), true);
+        assert_eq!(fold.apply("CAFE\u{301}"), COMPOSED);
+        assert_eq!(fold.apply("CAF\u{c9}"), COMPOSED);
+    }
+}
@@ -1,28 +1,118 @@
 use std::env;
+use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::io;
+use std::process;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
 
-/// Can be used to represent a coordinate on the map, or a slope delta
+mod render;
+
+use render::Trail;
+
+/// Coordinate on the map
 #[derive(Debug)]
 struct Point {
     x: usize,
     y: usize
 }
 
-#[derive(Debug, PartialEq)]
-enum Tile {
-    Space,
-    Tree
+/// How far the toboggan moves on each step. A negative x goes left, and a negative y
+/// starts at the bottom of the map and goes up. The map wraps around horizontally in
+/// both directions.
+#[derive(Debug)]
+struct Slope {
+    x: i64,
+    y: i64
+}
+
+/// Signed number written as `3`, `-3` or `1/3`
+#[derive(Debug)]
+struct Fraction {
+    numerator: i64,
+    denominator: i64
+}
+
+/// A kind of ground, and how much it costs the toboggan to go through it
+#[derive(Debug)]
+struct Terrain {
+    name: String,
+    /// used when showing the map
+    symbol: char,
+    cost: u64
+}
+
+/// Index of the terrain of a tile in the legend
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Tile(usize);
+
+/// Terrains a map is made of, and the symbols used for them in a map file
+#[derive(Debug)]
+struct Legend {
+    terrains: Vec<Terrain>,
+    symbols: HashMap<char, Tile>
+}
+
+/// What the toboggan went through on its way down a slope
+#[derive(Debug)]
+struct Survey {
+    /// number of tiles visited for each terrain of the legend
+    counts: Vec<usize>,
+    cost: u64
+}
+
+/// Why a map could not be loaded. Rows, columns and lines start at 1, and legend
+/// line 0 means the legend as a whole.
+#[derive(Debug)]
+enum MapError {
+    Io(io::Error),
+    Empty,
+    Ragged { row: usize, width: usize, expected: usize },
+    UnknownTile { row: usize, column: usize, symbol: char },
+    BadLegend { line: usize, reason: String }
 }
 
 /// Coordinate 0,0 represents the top left corner
 #[derive(Debug)]
-struct Map(Vec<Vec<Tile>>);
+struct Map {
+    tiles: Vec<Vec<Tile>>,
+    legend: Legend
+}
 
-impl Point {
+fn gcd(a: i64, b: i64) -> i64 {
+    match b {
+        0 => a.abs(),
+        _ => gcd(b, a % b)
+    }
+}
 
-    fn read(path: &str) -> Vec<Point> {
+impl Fraction {
+
+    fn parse(text: &str) -> Fraction {
+        let mut parts = text.trim().split('/');
+        let numerator = parts.next()
+            .expect("fraction is missing numerator")
+            .parse()
+            .expect("cannot parse numerator");
+
+        let denominator = parts.next()
+            .map(|d| d.parse().expect("cannot parse denominator"))
+            .unwrap_or(1);
+
+        if denominator <= 0 {
+            panic!("denominator must be positive");
+        }
+
+        Fraction { numerator, denominator }
+    }
+}
+
+impl Slope {
+
+    fn read(path: &str) -> Vec<Slope> {
         let file = File::open(path).expect("cannot open file");
         let buffer = BufReader::new(file);
         buffer.lines()
@@ -31,98 +121,568 @@ impl Point {
             .collect()
     }
 
-    fn parse(text: &str) -> Point {
-        let mut parts = text.split(",");
-        let x = parts.next()
-            .expect("slope is missing x")
-            .parse()
-            .expect("cannot parse slope x");
+    /// Parse a line like `3,1`, `-1,2` or `1/3,1`
+    fn parse(text: &str) -> Slope {
+        let mut parts = text.split(',');
+        let x = Fraction::parse(parts.next().expect("slope is missing x"));
+        let y = Fraction::parse(parts.next().expect("slope is missing y"));
+        Self::new(x, y)
+    }
 
-        let y = parts.next()
-            .expect("slope is missing y")
-            .parse()
-            .expect("cannot parse slope y");
+    /// Fractional slopes only land on a tile every few steps. Those steps are combined
+    /// into a single one that goes from tile to tile.
+    fn new(x: Fraction, y: Fraction) -> Slope {
+        let denominator = x.denominator * y.denominator / gcd(x.denominator, y.denominator);
+        let dx = x.numerator * (denominator / x.denominator);
+        let dy = y.numerator * (denominator / y.denominator);
+
+        if dy == 0 {
+            panic!("slope never leaves the map");
+        }
 
-        Point{x, y}
+        let divisor = gcd(gcd(dx, dy), denominator);
+        Slope {
+            x: dx / divisor,
+            y: dy / divisor
+        }
     }
+}
 
-    fn new(x: usize, y: usize) -> Point {
-        Point {x, y}
+impl fmt::Display for Slope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
     }
+}
+
+impl Point {
 
-    fn off_map(&self, map: &Map) -> bool {
-        self.y >= map.height()
+    fn start(slope: &Slope, map: &Map) -> Point {
+        match slope.y < 0 {
+            true => Point { x: 0, y: map.height() - 1 },
+            false => Point { x: 0, y: 0 }
+        }
     }
 
-    fn advance(&mut self, slope: &Point, map: &Map) {
-        self.x += slope.x;
-        self.y += slope.y;
-        if self.x >= map.width() {
-            self.x -= map.width();
+    /// Move along the slope. Returns None when falling off the top or bottom of the map.
+    fn advance(&self, slope: &Slope, map: &Map) -> Option<Point> {
+        let x = (self.x as i64 + slope.x).rem_euclid(map.width() as i64);
+        let y = self.y as i64 + slope.y;
+        if y < 0 || y >= map.height() as i64 {
+            return None;
         }
+        Some(Point { x: x as usize, y: y as usize })
+    }
+}
+
+impl Legend {
+
+    /// Open ground is free, trees cost 1 so that the cost of a slope is its number of trees
+    fn new(open: &str, trees: &str) -> Result<Legend, MapError> {
+        let error = |reason| MapError::BadLegend { line: 0, reason };
+        let mut legend = Legend { terrains: Vec::new(), symbols: HashMap::new() };
+        legend.add("open", open, 0).map_err(error)?;
+        legend.add("tree", trees, 1).map_err(error)?;
+        Ok(legend)
+    }
+
+    /// Each terrain needs at least one symbol, and a symbol can only belong to one terrain
+    fn add(&mut self, name: &str, symbols: &str, cost: u64) -> Result<(), String> {
+        if symbols.is_empty() {
+            return Err(format!("terrain {} has no symbols", name));
+        }
+        if let Some(symbol) = symbols.chars().find(|s| self.symbols.contains_key(s)) {
+            return Err(format!("symbol {:?} is used more than once", symbol));
+        }
+
+        let tile = Tile(self.terrains.len());
+        self.terrains.push(Terrain {
+            name: name.to_string(),
+            symbol: symbols.chars().next().unwrap_or(' '),
+            cost
+        });
+        for symbol in symbols.chars() {
+            self.symbols.insert(symbol, tile);
+        }
+        Ok(())
+    }
+
+    /// Read a legend file where each line gives the symbols of a terrain, its name and its cost:
+    ///
+    /// ```text
+    /// . open 0
+    /// # tree 1
+    /// ^R rock 5
+    /// ```
+    fn read(path: &str) -> Result<Legend, MapError> {
+        let file = File::open(path)?;
+        let lines = BufReader::new(file)
+            .lines()
+            .collect::<Result<Vec<String>, io::Error>>()?;
+        Self::parse(&lines)
+    }
+
+    fn parse(lines: &[String]) -> Result<Legend, MapError> {
+        let mut legend = Legend { terrains: Vec::new(), symbols: HashMap::new() };
+
+        for (i, line) in lines.iter().enumerate() {
+            let error = |reason: &str| MapError::BadLegend { line: i + 1, reason: reason.to_string() };
+
+            let mut parts = line.split_whitespace();
+            let symbols = match parts.next() {
+                Some(symbols) => symbols,
+                None => continue
+            };
+            let name = parts.next().ok_or_else(|| error("terrain is missing a name"))?;
+            let cost = parts.next()
+                .ok_or_else(|| error("terrain is missing a cost"))?
+                .parse()
+                .map_err(|_| error("cannot parse cost"))?;
+
+            legend.add(name, symbols, cost).map_err(|reason| error(&reason))?;
+        }
+
+        match legend.terrains.is_empty() {
+            true => Err(MapError::BadLegend { line: 0, reason: "legend is empty".to_string() }),
+            false => Ok(legend)
+        }
+    }
+
+    fn get(&self, tile: Tile) -> &Terrain {
+        &self.terrains[tile.0]
+    }
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Io(error) => write!(f, "cannot read map: {}", error),
+            MapError::Empty => write!(f, "map is empty"),
+            MapError::Ragged { row, width, expected } =>
+                write!(f, "row {} has {} tiles, expected {}", row, width, expected),
+            MapError::UnknownTile { row, column, symbol } =>
+                write!(f, "unknown tile {:?} at row {}, column {}", symbol, row, column),
+            MapError::BadLegend { line: 0, reason } => write!(f, "legend: {}", reason),
+            MapError::BadLegend { line, reason } =>
+                write!(f, "legend line {}: {}", line, reason)
+        }
+    }
+}
+
+impl From<io::Error> for MapError {
+    fn from(error: io::Error) -> MapError {
+        MapError::Io(error)
     }
 }
 
 impl Map {
 
-    fn parse(path: &str) -> Map {
-        let file = File::open(path).expect("cannot open file");
-        let buffer = BufReader::new(file);
-        let tiles = buffer.lines()
-            .map(|result| result.expect("cannot read line"))
-            .map(|line| {
-                line.chars().map(|c| match c {
-                    '.' => Tile::Space,
-                    '#' => Tile::Tree,
-                    _ => panic!("unexpected tile char")
-                })
-                .collect()
-            })
-            .collect();
-        Map(tiles)
+    fn read(path: &str, legend: Legend) -> Result<Map, MapError> {
+        let file = File::open(path)?;
+        let lines = BufReader::new(file)
+            .lines()
+            .collect::<Result<Vec<String>, io::Error>>()?;
+        Self::parse(&lines, legend)
+    }
+
+    /// Every row must have the same number of tiles, and there must be at least one tile
+    fn parse(lines: &[String], legend: Legend) -> Result<Map, MapError> {
+        let mut rows: Vec<Vec<Tile>> = Vec::with_capacity(lines.len());
+
+        for (y, line) in lines.iter().enumerate() {
+            let row = line.chars()
+                .enumerate()
+                .map(|(x, symbol)| legend.symbols.get(&symbol)
+                    .cloned()
+                    .ok_or(MapError::UnknownTile { row: y + 1, column: x + 1, symbol }))
+                .collect::<Result<Vec<Tile>, MapError>>()?;
+
+            if let Some(first) = rows.first() {
+                if row.len() != first.len() {
+                    return Err(MapError::Ragged { row: y + 1, width: row.len(), expected: first.len() });
+                }
+            }
+            rows.push(row);
+        }
+
+        match rows.first() {
+            Some(row) if !row.is_empty() => Ok(Map { tiles: rows, legend }),
+            _ => Err(MapError::Empty)
+        }
     }
 
     fn height(&self) -> usize {
-        self.0.len()
+        self.tiles.len()
     }
 
     fn width(&self) -> usize {
-        //i'm trusting that all rows in the map have the same length
-        self.0[0].len()
+        // all rows have the same length, this is checked when parsing
+        self.tiles[0].len()
     }
 
-    fn get(&self, point: &Point) -> &Tile {
-        &self.0[point.y][point.x]
+    fn get(&self, point: &Point) -> Tile {
+        self.tiles[point.y][point.x]
     }
 }
 
-fn count_trees(map: &Map, slope: &Point) -> usize {
-    let mut count = 0;
-    let mut point = Point::new(0, 0);
+impl Survey {
 
-    while !point.off_map(&map) {
-        if map.get(&point) == &Tile::Tree {
-            count += 1;
+    fn new(map: &Map, slope: &Slope) -> Survey {
+        let mut counts = vec![0; map.legend.terrains.len()];
+        let mut point = Some(Point::start(slope, map));
+
+        while let Some(current) = point {
+            counts[map.get(&current).0] += 1;
+            point = current.advance(slope, map);
         }
-        point.advance(&slope, &map);
+
+        let cost = counts.iter()
+            .zip(map.legend.terrains.iter())
+            .map(|(count, terrain)| *count as u64 * terrain.cost)
+            .sum();
+
+        Survey { counts, cost }
     }
 
-    count
+    /// Count of each terrain, like `open 4 tree 7`
+    fn describe(&self, legend: &Legend) -> String {
+        legend.terrains.iter()
+            .zip(self.counts.iter())
+            .map(|(terrain, count)| format!("{} {}", terrain.name, count))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+/// Parse bounds written as `-3..3`
+fn parse_range(text: &str) -> RangeInclusive<i64> {
+    let (start, end) = text.split_once("..").expect("range is missing ..");
+    let start = start.parse().expect("cannot parse range start");
+    let end = end.parse().expect("cannot parse range end");
+    start..=end
+}
+
+/// Cost of every slope within the bounds, ranked from cheapest to most expensive.
+/// Slopes that follow the same path are only walked once: moving x by a multiple of the
+/// map width lands on the same tiles, and any y that leaves the map after the first tile
+/// only ever sees that tile.
+fn search(map: &Map, xs: RangeInclusive<i64>, ys: RangeInclusive<i64>) -> Vec<(Slope, u64)> {
+    let width = map.width() as i64;
+    let height = map.height() as i64;
+    let mut paths: HashMap<(i64, i64), u64> = HashMap::new();
+    let mut ranking = Vec::new();
+
+    for y in ys.filter(|y| *y != 0) {
+        for x in xs.clone() {
+            let path = match y.abs() >= height {
+                true => (0, y.signum() * height),
+                false => (x.rem_euclid(width), y)
+            };
+            let cost = *paths.entry(path)
+                .or_insert_with(|| Survey::new(map, &Slope { x: path.0, y: path.1 }).cost);
+            ranking.push((Slope { x, y }, cost));
+        }
+    }
+
+    ranking.sort_by_key(|(_, cost)| *cost);
+    ranking
+}
+
+fn print_search(ranking: &[(Slope, u64)]) {
+    for (slope, cost) in ranking {
+        println!("{} {}", slope, cost);
+    }
+
+    let (lowest, highest) = match (ranking.first(), ranking.last()) {
+        (Some(first), Some(last)) => (first.1, last.1),
+        _ => return
+    };
+
+    let slopes = |wanted: u64| ranking.iter()
+        .filter(|(_, cost)| *cost == wanted)
+        .map(|(slope, _)| slope.to_string())
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    println!("lowest cost {}: {}", lowest, slopes(lowest));
+    println!("highest cost {}: {}", highest, slopes(highest));
+}
+
+/// Print the trail of a slope, and write it to image files when asked to
+fn render(map: &Map, slope: &Slope, mut args: impl Iterator<Item = String>) {
+    let mut tile = false;
+    let mut color = false;
+    let mut svg = None;
+    let mut png = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tile" => tile = true,
+            "--color" => color = true,
+            "--svg" => svg = Some(args.next().expect("cannot find svg file path")),
+            "--png" => png = Some(args.next().expect("cannot find png file path")),
+            _ => panic!("unknown render option {}", arg)
+        }
+    }
+
+    let trail = Trail::new(map, slope, tile);
+    print!("{}", trail.text(color));
+
+    if let Some(path) = svg {
+        fs::write(path, trail.svg()).expect("cannot write svg file");
+    }
+    if let Some(path) = png {
+        fs::write(path, trail.png()).expect("cannot write png file");
+    }
 }
 
 fn main() {
-    let path = env::args().nth(1).expect("cannot fild map file path");
-    let map = Map::parse(&path);
+    // options for the map can be anywhere, everything else keeps its position
+    let mut open = ".".to_string();
+    let mut trees = "#".to_string();
+    let mut legend = None;
+    let mut args = Vec::new();
 
-    let path = env::args().nth(2).expect("cannot find slope file path");
-    let slopes = Point::read(&path);
+    let mut all = env::args().skip(1);
+    while let Some(arg) = all.next() {
+        match arg.as_str() {
+            "--open" => open = all.next().expect("cannot find open tile symbols"),
+            "--tree" => trees = all.next().expect("cannot find tree tile symbols"),
+            "--legend" => legend = Some(all.next().expect("cannot find legend file path")),
+            _ => args.push(arg)
+        }
+    }
+    let mut args = args.into_iter();
+
+    let path = args.next().expect("cannot fild map file path");
+    let legend = match legend {
+        Some(legend) => Legend::read(&legend),
+        None => Legend::new(&open, &trees)
+    };
+
+    let map = legend.and_then(|legend| Map::read(&path, legend))
+        .unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            process::exit(1);
+        });
+
+    let path = args.next().expect("cannot find slope file path");
+    if path == "--search" {
+        let xs = parse_range(&args.next().expect("cannot find x range"));
+        let ys = parse_range(&args.next().expect("cannot find y range"));
+        print_search(&search(&map, xs, ys));
+        return;
+    }
 
-    let trees: Vec<usize> = slopes.iter()
-        .map(|slope| count_trees(&map, slope))
+    if path == "--render" {
+        let slope = Slope::parse(&args.next().expect("cannot find slope"));
+        render(&map, &slope, args);
+        return;
+    }
+
+    let slopes = Slope::read(&path);
+
+    let surveys: Vec<Survey> = slopes.iter()
+        .map(|slope| Survey::new(&map, slope))
         .collect();
 
-    let product: usize = trees.iter().product();
+    for (slope, survey) in slopes.iter().zip(surveys.iter()) {
+        println!("{} {} cost {}", slope, survey.describe(&map.legend), survey.cost);
+    }
+
+    let costs: Vec<u64> = surveys.iter().map(|s| s.cost).collect();
+    let product: u64 = costs.iter().product();
 
-    println!("{:?}", trees);
+    println!("{:?}", costs);
     println!("{}", product);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(lines: &[&str]) -> Map {
+        parse(lines).expect("cannot parse map")
+    }
+
+    fn parse(lines: &[&str]) -> Result<Map, MapError> {
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        Map::parse(&lines, Legend::new(".", "#").expect("cannot build legend"))
+    }
+
+    /// Every point visited along the slope, starting point included
+    fn path(map: &Map, slope: &str) -> Vec<(usize, usize)> {
+        let slope = Slope::parse(slope);
+        let mut points = Vec::new();
+        let mut point = Some(Point::start(&slope, map));
+        while let Some(current) = point {
+            points.push((current.x, current.y));
+            point = current.advance(&slope, map);
+        }
+        points
+    }
+
+    #[test]
+    fn parses_maps() {
+        let map = map(&["..#", "#.."]);
+        assert_eq!((map.width(), map.height()), (3, 2));
+        assert_eq!(map.get(&Point { x: 2, y: 0 }), Tile(1));
+        assert_eq!(map.get(&Point { x: 1, y: 1 }), Tile(0));
+    }
+
+    #[test]
+    fn rejects_empty_maps() {
+        assert!(matches!(parse(&[]), Err(MapError::Empty)));
+        assert!(matches!(parse(&["", ""]), Err(MapError::Empty)));
+    }
+
+    #[test]
+    fn rejects_ragged_maps() {
+        assert!(matches!(parse(&["...", "..", "..."]), Err(MapError::Ragged { row: 2, width: 2, expected: 3 })));
+        assert!(matches!(parse(&["...", "...", "...."]), Err(MapError::Ragged { row: 3, width: 4, expected: 3 })));
+    }
+
+    #[test]
+    fn rejects_unknown_tiles() {
+        assert!(matches!(parse(&["...", ".#x"]), Err(MapError::UnknownTile { row: 2, column: 3, symbol: 'x' })));
+        assert!(matches!(parse(&["^.."]), Err(MapError::UnknownTile { row: 1, column: 1, symbol: '^' })));
+    }
+
+    #[test]
+    fn builds_legends_from_symbols() {
+        let legend = Legend::new(".o", "#T").expect("cannot build legend");
+        assert_eq!(legend.symbols.get(&'o'), Some(&Tile(0)));
+        assert_eq!(legend.symbols.get(&'T'), Some(&Tile(1)));
+        assert_eq!(legend.get(Tile(1)).symbol, '#');
+    }
+
+    #[test]
+    fn rejects_overlapping_or_empty_symbols() {
+        for (open, trees) in [("#", "#"), (".#", "#"), (".", ".T")] {
+            match Legend::new(open, trees) {
+                Err(MapError::BadLegend { line: 0, reason }) => assert!(reason.contains("more than once"), "{}", reason),
+                result => panic!("unexpected result {:?}", result)
+            }
+        }
+        for (open, trees) in [("", "#"), (".", "")] {
+            match Legend::new(open, trees) {
+                Err(error) => assert!(error.to_string().ends_with("has no symbols"), "{}", error),
+                result => panic!("unexpected result {:?}", result)
+            }
+        }
+    }
+
+    fn legend(lines: &[&str]) -> Result<Legend, MapError> {
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        Legend::parse(&lines)
+    }
+
+    /// Reason given for a bad legend, and the line it is on
+    fn legend_error(lines: &[&str]) -> (usize, String) {
+        match legend(lines) {
+            Err(MapError::BadLegend { line, reason }) => (line, reason),
+            result => panic!("unexpected result {:?}", result)
+        }
+    }
+
+    #[test]
+    fn parses_legends() {
+        let legend = legend(&[". open 0", "", "#T tree 1", "^ rock 5"]).expect("cannot parse legend");
+        let terrains: Vec<(&str, char, u64)> = legend.terrains.iter()
+            .map(|terrain| (terrain.name.as_str(), terrain.symbol, terrain.cost))
+            .collect();
+        assert_eq!(terrains, vec![("open", '.', 0), ("tree", '#', 1), ("rock", '^', 5)]);
+        assert_eq!(legend.symbols.get(&'T'), Some(&Tile(1)));
+    }
+
+    #[test]
+    fn rejects_bad_legends() {
+        assert_eq!(legend_error(&[". open 0", "#"]), (2, "terrain is missing a name".to_string()));
+        assert_eq!(legend_error(&[". open"]), (1, "terrain is missing a cost".to_string()));
+        assert_eq!(legend_error(&[". open 0", "# tree -1"]), (2, "cannot parse cost".to_string()));
+        assert_eq!(legend_error(&[". open zero"]), (1, "cannot parse cost".to_string()));
+        assert_eq!(legend_error(&[". open 0", "# tree 1", "^# rock 5"]),
+                   (3, "symbol '#' is used more than once".to_string()));
+        assert_eq!(legend_error(&[]), (0, "legend is empty".to_string()));
+        assert_eq!(legend_error(&["", "  "]), (0, "legend is empty".to_string()));
+    }
+
+    #[test]
+    fn surveys_count_each_terrain() {
+        let legend = legend(&[". open 0", "# tree 1", "^ rock 5"]).expect("cannot parse legend");
+        let lines: Vec<String> = ["#.^", ".^#", "^^.", "#.."].iter().map(|line| line.to_string()).collect();
+        let map = Map::parse(&lines, legend).expect("cannot parse map");
+
+        let survey = Survey::new(&map, &Slope::parse("1,1"));
+        assert_eq!(survey.counts, vec![1, 2, 1]);
+        assert_eq!(survey.cost, 2 + 5);
+        assert_eq!(survey.describe(&map.legend), "open 1 tree 2 rock 1");
+
+        let survey = Survey::new(&map, &Slope::parse("2,1"));
+        assert_eq!(survey.counts, vec![0, 3, 1]);
+        assert_eq!(survey.cost, 3 + 5);
+    }
+
+    #[test]
+    fn fractional_slopes_step_from_tile_to_tile() {
+        assert_eq!(Slope::parse("1/2,1").to_string(), "1,2");
+        assert_eq!(Slope::parse("2/4,1").to_string(), "1,2");
+        assert_eq!(Slope::parse("1/3,-1/2").to_string(), "2,-3");
+        assert_eq!(Slope::parse("-3,1").to_string(), "-3,1");
+        assert_eq!(Slope::parse("4,2").to_string(), "4,2");
+    }
+
+    #[test]
+    fn negative_x_wraps_left() {
+        let map = map(&["...", "...", "...", "..."]);
+        assert_eq!(path(&map, "-1,1"), vec![(0, 0), (2, 1), (1, 2), (0, 3)]);
+        assert_eq!(path(&map, "-4,1"), path(&map, "-1,1"));
+        assert_eq!(path(&map, "5,1"), vec![(0, 0), (2, 1), (1, 2), (0, 3)]);
+    }
+
+    #[test]
+    fn negative_y_starts_on_the_last_row() {
+        let map = map(&["...", "...", "..."]);
+        assert_eq!(path(&map, "1,-1"), vec![(0, 2), (1, 1), (2, 0)]);
+        assert_eq!(path(&map, "1,-2"), vec![(0, 2), (1, 0)]);
+        assert_eq!(path(&map, "1,-3"), vec![(0, 2)]);
+    }
+
+    #[test]
+    fn search_matches_surveys() {
+        let legend = Legend::new(".", "#").expect("cannot build legend");
+        let map = Map::read("input/sample", legend).expect("cannot read sample map");
+        assert_eq!((map.width(), map.height()), (11, 11));
+
+        let ranking = search(&map, -3..=25, -12..=13);
+        assert_eq!(ranking.len(), 29 * 25);
+        assert!(ranking.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        for (slope, cost) in &ranking {
+            assert_eq!(*cost, Survey::new(&map, slope).cost, "slope {}", slope);
+        }
+        assert_eq!(ranking.iter().find(|(slope, _)| (slope.x, slope.y) == (3, 1)).unwrap().1, 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "slope never leaves the map")]
+    fn rejects_flat_slopes() {
+        Slope::parse("1,0");
+    }
+
+    #[test]
+    #[should_panic(expected = "slope never leaves the map")]
+    fn rejects_flat_fractional_slopes() {
+        Slope::parse("1/2,0/3");
+    }
+
+    #[test]
+    #[should_panic(expected = "denominator must be positive")]
+    fn rejects_zero_denominators() {
+        Fraction::parse("1/0");
+    }
+
+    #[test]
+    #[should_panic(expected = "denominator must be positive")]
+    fn rejects_negative_denominators() {
+        Fraction::parse("1/-2");
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{Map, Point, Slope};
+
+/// Size of a tile in pixels when drawing images
+const SCALE: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    symbol: char,
+    /// the terrain has a cost to go through, like trees
+    obstacle: bool,
+    /// the toboggan went through this tile
+    visited: bool
+}
+
+/// The map with the path of a slope drawn on top of it
+pub struct Trail(Vec<Vec<Cell>>);
+
+impl Cell {
+
+    /// Visited tiles are drawn as O when open, or X when hitting an obstacle
+    fn symbol(&self) -> char {
+        match (self.visited, self.obstacle) {
+            (true, true) => 'X',
+            (true, false) => 'O',
+            _ => self.symbol
+        }
+    }
+
+    /// Escape code used to color the cell in a terminal
+    fn ansi(&self) -> &'static str {
+        match (self.visited, self.obstacle) {
+            (false, false) => "\x1b[2m",
+            (false, true) => "\x1b[32m",
+            (true, false) => "\x1b[1;33m",
+            (true, true) => "\x1b[1;31m"
+        }
+    }
+
+    fn rgb(&self) -> [u8; 3] {
+        match (self.visited, self.obstacle) {
+            (false, false) => [0x0f, 0x0f, 0x23],
+            (false, true) => [0x00, 0x99, 0x00],
+            (true, false) => [0xff, 0xff, 0x66],
+            (true, true) => [0xff, 0x00, 0x00]
+        }
+    }
+
+    fn background(&self) -> bool {
+        !self.visited && !self.obstacle
+    }
+}
+
+impl Trail {
+
+    /// Follow the slope down the map. When `tile` is set, the map is repeated to the left and right
+    /// as many times as the path needs, instead of wrapping the path around a single map.
+    pub fn new(map: &Map, slope: &Slope, tile: bool) -> Trail {
+        let width = map.width() as i64;
+
+        // walk the path without wrapping, to know how far it goes left and right
+        let mut path = Vec::new();
+        let mut x = 0;
+        let mut point = Some(Point::start(slope, map));
+        while let Some(current) = point {
+            path.push((x, current.y));
+            x += slope.x;
+            point = current.advance(slope, map);
+        }
+
+        let (first, copies) = match tile {
+            true => {
+                let first = path.iter().map(|(x, _)| x.div_euclid(width)).min().unwrap_or(0);
+                let last = path.iter().map(|(x, _)| x.div_euclid(width)).max().unwrap_or(0);
+                (first, (last - first + 1) as usize)
+            },
+            false => (0, 1)
+        };
+
+        let mut cells: Vec<Vec<Cell>> = map.tiles.iter()
+            .map(|row| {
+                row.iter()
+                    .map(|tile| {
+                        let terrain = map.legend.get(*tile);
+                        Cell { symbol: terrain.symbol, obstacle: terrain.cost > 0, visited: false }
+                    })
+                    .cycle()
+                    .take(row.len() * copies)
+                    .collect()
+            })
+            .collect();
+
+        for (x, y) in path {
+            let column = match tile {
+                true => (x - first * width) as usize,
+                false => x.rem_euclid(width) as usize
+            };
+            cells[y][column].visited = true;
+        }
+
+        Trail(cells)
+    }
+
+    pub fn text(&self, color: bool) -> String {
+        let mut text = String::new();
+        for row in &self.0 {
+            for cell in row {
+                if color {
+                    text.push_str(cell.ansi());
+                }
+                text.push(cell.symbol());
+            }
+            if color {
+                text.push_str("\x1b[0m");
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn width(&self) -> usize {
+        self.0.first().map(|row| row.len()).unwrap_or(0)
+    }
+
+    pub fn svg(&self) -> String {
+        let width = self.width() * SCALE;
+        let height = self.0.len() * SCALE;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+            width, height, width, height
+        );
+
+        let [r, g, b] = Cell { symbol: ' ', obstacle: false, visited: false }.rgb();
+        svg.push_str(&format!("<rect width=\"100%\" height=\"100%\" fill=\"#{:02x}{:02x}{:02x}\"/>\n", r, g, b));
+
+        for (y, row) in self.0.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if cell.background() {
+                    continue;
+                }
+                let [r, g, b] = cell.rgb();
+                svg.push_str(&format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#{:02x}{:02x}{:02x}\"/>\n",
+                    x * SCALE, y * SCALE, SCALE, SCALE, r, g, b
+                ));
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Encode the trail as an RGB PNG image
+    pub fn png(&self) -> Vec<u8> {
+        let width = self.width() * SCALE;
+        let height = self.0.len() * SCALE;
+
+        let mut pixels = Vec::with_capacity(height * width * 3);
+        for row in &self.0 {
+            let mut scanline = Vec::with_capacity(width * 3);
+            for cell in row {
+                for _ in 0..SCALE {
+                    scanline.extend_from_slice(&cell.rgb());
+                }
+            }
+            for _ in 0..SCALE {
+                pixels.extend_from_slice(&scanline);
+            }
+        }
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().expect("cannot write png header");
+        writer.write_image_data(&pixels).expect("cannot write png data");
+        writer.finish().expect("cannot finish png");
+        png
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Legend, Slope};
+
+    fn trail(lines: &[&str], slope: &str, tile: bool) -> Trail {
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        let map = Map::parse(&lines, Legend::new(".", "#").expect("cannot build legend")).expect("cannot parse map");
+        Trail::new(&map, &Slope::parse(slope), tile)
+    }
+
+    /// Decode a png into its size and pixels
+    fn decode(png: &[u8]) -> (u32, u32, Vec<u8>) {
+        let decoder = png::Decoder::new(png);
+        let mut reader = decoder.read_info().expect("cannot read png header");
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).expect("cannot read png data");
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        assert_eq!(info.bit_depth, png::BitDepth::Eight);
+        pixels.truncate(info.buffer_size());
+        (info.width, info.height, pixels)
+    }
+
+    fn pixel(pixels: &[u8], width: u32, x: usize, y: usize) -> [u8; 3] {
+        let start = (y * width as usize + x) * 3;
+        [pixels[start], pixels[start + 1], pixels[start + 2]]
+    }
+
+    #[test]
+    fn text() {
+        let trail = trail(&["..#", "#..", ".#."], "1,1", false);
+        assert_eq!(trail.text(false), "O.#\n#O.\n.#O\n");
+    }
+
+    #[test]
+    fn text_wraps_around() {
+        let trail = trail(&["...", "...", "..#"], "2,1", false);
+        assert_eq!(trail.text(false), "O..\n..O\n.O#\n");
+    }
+
+    #[test]
+    fn text_tiled() {
+        let trail = trail(&["...", "...", "..#"], "2,1", true);
+        assert_eq!(trail.text(false), "O.....\n..O...\n..#.O#\n");
+    }
+
+    #[test]
+    fn png_decodes_to_the_trail() {
+        let trail = trail(&["..#", "#..", ".##"], "1,1", false);
+        let (width, height, pixels) = decode(&trail.png());
+        assert_eq!((width, height), (3 * SCALE as u32, 3 * SCALE as u32));
+        assert_eq!(pixels.len(), 9 * SCALE * SCALE * 3);
+
+        let open = Cell { symbol: '.', obstacle: false, visited: false }.rgb();
+        let tree = Cell { symbol: '#', obstacle: true, visited: false }.rgb();
+        let path = Cell { symbol: '.', obstacle: false, visited: true }.rgb();
+        let crash = Cell { symbol: '#', obstacle: true, visited: true }.rgb();
+        let expected = [[path, open, tree], [tree, path, open], [open, tree, crash]];
+
+        for y in 0..3 * SCALE {
+            for x in 0..3 * SCALE {
+                assert_eq!(pixel(&pixels, width, x, y), expected[y / SCALE][x / SCALE], "pixel {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn png_of_sample() {
+        let lines: Vec<String> = include_str!("../input/sample").lines().map(|line| line.to_string()).collect();
+        let map = Map::parse(&lines, Legend::new(".", "#").expect("cannot build legend")).expect("cannot parse map");
+        let trail = Trail::new(&map, &Slope::parse("3,1"), true);
+
+        let (width, height, pixels) = decode(&trail.png());
+        assert_eq!(width as usize, trail.width() * SCALE);
+        assert_eq!(height as usize, map.height() * SCALE);
+
+        let crash = Cell { symbol: '#', obstacle: true, visited: true }.rgb();
+        let crashes = (0..map.height())
+            .flat_map(|y| (0..trail.width()).map(move |x| (x, y)))
+            .filter(|(x, y)| pixel(&pixels, width, x * SCALE, y * SCALE) == crash)
+            .count();
+        assert_eq!(crashes, 7);
+    }
+}
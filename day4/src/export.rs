@@ -0,0 +1,270 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::Passport;
+
+const CENTIMETERS_PER_INCH: f64 = 2.54;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthUnit {
+    Cm,
+    In
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Height {
+    pub value: u32,
+    pub unit: LengthUnit
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum EyeColor {
+    #[serde(rename = "amb")]
+    Amber,
+    #[serde(rename = "blu")]
+    Blue,
+    #[serde(rename = "brn")]
+    Brown,
+    #[serde(rename = "gry")]
+    Gray,
+    #[serde(rename = "grn")]
+    Green,
+    #[serde(rename = "hzl")]
+    Hazel,
+    #[serde(rename = "oth")]
+    Other
+}
+
+/// A passport whose fields have been read into their own types
+#[derive(Debug, Serialize)]
+pub struct ValidPassport {
+    pub record: usize,
+    pub birth_year: u16,
+    pub issue_year: u16,
+    pub expiration_year: u16,
+    pub height: Height,
+    /// height converted to centimeters, rounded to hundredths
+    pub height_cm: f64,
+    pub hair_color: Color,
+    pub eye_color: EyeColor,
+    /// kept as text, leading zeroes are part of the id
+    pub passport_id: String,
+    /// kept as text, the schema puts no rule on it
+    pub country_id: Option<String>
+}
+
+/// Format used to write valid passports
+pub enum Export {
+    Csv,
+    /// one JSON object on each line
+    JsonLines
+}
+
+/// Text fields are quoted when they contain a separator or a quote
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string()
+    }
+}
+
+impl Height {
+
+    /// `183cm` or `74in`
+    fn parse(text: &str) -> Option<Height> {
+        let split = text.find(|c: char| !c.is_ascii_digit())?;
+        let (value, unit) = text.split_at(split);
+        let unit = match unit {
+            "cm" => LengthUnit::Cm,
+            "in" => LengthUnit::In,
+            _ => return None
+        };
+        Some(Height { value: value.parse().ok()?, unit })
+    }
+
+    /// The height measured in another unit
+    pub fn to(&self, unit: LengthUnit) -> f64 {
+        let value = self.value as f64;
+        match (self.unit, unit) {
+            (LengthUnit::In, LengthUnit::Cm) => value * CENTIMETERS_PER_INCH,
+            (LengthUnit::Cm, LengthUnit::In) => value / CENTIMETERS_PER_INCH,
+            _ => value
+        }
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LengthUnit::Cm => write!(f, "cm"),
+            LengthUnit::In => write!(f, "in")
+        }
+    }
+}
+
+impl Color {
+
+    /// Colors are written like `#1a2b3c`
+    fn parse(text: &str) -> Option<Color> {
+        let hex = text.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Color { red: channel(0)?, green: channel(2)?, blue: channel(4)? })
+    }
+}
+
+impl EyeColor {
+
+    fn parse(code: &str) -> Option<EyeColor> {
+        match code {
+            "amb" => Some(EyeColor::Amber),
+            "blu" => Some(EyeColor::Blue),
+            "brn" => Some(EyeColor::Brown),
+            "gry" => Some(EyeColor::Gray),
+            "grn" => Some(EyeColor::Green),
+            "hzl" => Some(EyeColor::Hazel),
+            "oth" => Some(EyeColor::Other),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for EyeColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let code = match self {
+            EyeColor::Amber => "amb",
+            EyeColor::Blue => "blu",
+            EyeColor::Brown => "brn",
+            EyeColor::Gray => "gry",
+            EyeColor::Green => "grn",
+            EyeColor::Hazel => "hzl",
+            EyeColor::Other => "oth"
+        };
+        write!(f, "{}", code)
+    }
+}
+
+impl ValidPassport {
+
+    /// Read the fields of a passport that passed validation. A schema other than the default
+    /// one can let through values that don't fit these types, in which case None is returned.
+    pub fn new(passport: &Passport) -> Option<ValidPassport> {
+        let height = Height::parse(passport.get("hgt")?)?;
+        Some(ValidPassport {
+            record: passport.record,
+            birth_year: passport.get("byr")?.parse().ok()?,
+            issue_year: passport.get("iyr")?.parse().ok()?,
+            expiration_year: passport.get("eyr")?.parse().ok()?,
+            height,
+            height_cm: (height.to(LengthUnit::Cm) * 100.0).round() / 100.0,
+            hair_color: Color::parse(passport.get("hcl")?)?,
+            eye_color: EyeColor::parse(passport.get("ecl")?)?,
+            passport_id: passport.get("pid")?.to_string(),
+            country_id: passport.get("cid").map(|cid| cid.to_string())
+        })
+    }
+}
+
+impl Export {
+
+    pub fn header(&self) {
+        if let Export::Csv = self {
+            println!(
+                "record,birth_year,issue_year,expiration_year,height,height_unit,height_cm,\
+                hair_red,hair_green,hair_blue,eye_color,passport_id,country_id"
+            );
+        }
+    }
+
+    pub fn passport(&self, passport: &ValidPassport) {
+        match self {
+            Export::Csv => println!(
+                "{},{},{},{},{},{},{:.2},{},{},{},{},{},{}",
+                passport.record,
+                passport.birth_year,
+                passport.issue_year,
+                passport.expiration_year,
+                passport.height.value,
+                passport.height.unit,
+                passport.height_cm,
+                passport.hair_color.red,
+                passport.hair_color.green,
+                passport.hair_color.blue,
+                passport.eye_color,
+                csv_field(&passport.passport_id),
+                csv_field(passport.country_id.as_deref().unwrap_or_default())
+            ),
+            Export::JsonLines => println!(
+                "{}",
+                serde_json::to_string(passport).expect("cannot serialize passport")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use records::Records;
+
+    fn export(text: &str) -> Option<ValidPassport> {
+        let record = Records::new(text.as_bytes()).next().unwrap().unwrap();
+        ValidPassport::new(&Passport::parse(&record))
+    }
+
+    const PASSPORT: &str = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f";
+
+    #[test]
+    fn typed_fields() {
+        let passport = export(PASSPORT).expect("cannot export passport");
+        assert_eq!(passport.birth_year, 1980);
+        assert_eq!(passport.height.value, 74);
+        assert_eq!(passport.height.unit, LengthUnit::In);
+        assert_eq!(passport.height_cm, 187.96);
+        assert_eq!((passport.hair_color.red, passport.hair_color.green, passport.hair_color.blue), (0x62, 0x3a, 0x2f));
+        assert_eq!(passport.passport_id, "087499704");
+        assert_eq!(passport.country_id, None);
+    }
+
+    #[test]
+    fn country_kept_as_text() {
+        let passport = export(&format!("{} cid:007", PASSPORT)).expect("cannot export passport");
+        assert_eq!(passport.country_id.as_deref(), Some("007"));
+        let passport = export(&format!("{} cid:abc", PASSPORT)).expect("cannot export passport");
+        assert_eq!(passport.country_id.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn csv_quotes() {
+        assert_eq!(csv_field("007"), "007");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn json_has_height_in_centimeters() {
+        let passport = export(&format!("{} cid:007", PASSPORT)).expect("cannot export passport");
+        let json: serde_json::Value = serde_json::to_value(&passport).expect("cannot serialize passport");
+        assert_eq!(json["height"], serde_json::json!({ "value": 74, "unit": "in" }));
+        assert_eq!(json["height_cm"], 187.96);
+        assert_eq!(json["country_id"], "007");
+    }
+
+    #[test]
+    fn conversions() {
+        let height = Height { value: 180, unit: LengthUnit::Cm };
+        assert_eq!(height.to(LengthUnit::Cm), 180.0);
+        assert!((height.to(LengthUnit::In) - 70.866).abs() < 0.001);
+    }
+}
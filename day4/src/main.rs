@@ -1,131 +1,507 @@
 use std::env;
+use std::fmt;
 use std::fs::File;
-use std::io::prelude::*;
 use std::io::BufReader;
-use std::collections::HashMap;
-use std::ops::RangeInclusive;
+use std::collections::HashSet;
+use std::process;
 
-const EYE_COLORS: [&str; 7] = ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
-const DIGITS: RangeInclusive<char> = '0'..='9';
-const LETTERS: RangeInclusive<char> = 'a'..='f';
-const HEIGHT_CM: RangeInclusive<u8> = 150..=193;
-const HEIGHT_IN: RangeInclusive<u8> = 59..=76;
+use serde::Serialize;
 
+mod export;
+mod schema;
+
+use records::{Record, Records};
+use export::{Export, ValidPassport};
+use schema::{Problem, Schema};
 
 #[derive(Debug)]
-struct Passport(HashMap<String, String>);
+struct Passport {
+    /// position of the passport in the file, starting at 1
+    record: usize,
+    /// line where the passport starts, starting at 1
+    line: usize,
+    /// fields in the order they were written, duplicates included
+    fields: Vec<(String, String)>,
+    /// tokens that aren't written as `key:value`
+    malformed: Vec<String>
+}
+
+/// Something wrong with a field of a passport
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Violation {
+    Malformed { token: String },
+    Missing { field: String },
+    Unknown { field: String },
+    Duplicate { field: String },
+    OutOfRange { field: String, value: String },
+    BadFormat { field: String, value: String }
+}
 
+/// Violations found in a passport
+#[derive(Debug, Serialize)]
+struct Diagnostic<'a> {
+    record: usize,
+    line: usize,
+    valid: bool,
+    violations: &'a [Violation]
+}
+
+enum Report {
+    Table,
+    Json
+}
 
 impl Passport {
 
-    fn new() -> Passport {
-        Passport(HashMap::new())
+    /// Fields are `key:value` tokens. Tokens without a colon are kept aside to be reported.
+    fn parse(record: &Record) -> Passport {
+        let mut fields = Vec::new();
+        let mut malformed = Vec::new();
+        for token in record.tokens() {
+            match token.split_once(':') {
+                Some((key, value)) => fields.push((key.to_string(), value.to_string())),
+                None => malformed.push(token.to_string())
+            }
+        }
+
+        Passport {
+            record: record.index,
+            line: record.line,
+            fields,
+            malformed
+        }
+    }
+
+    /// Value of the first field with this key
+    fn get(&self, key: &str) -> Option<&str> {
+        self.fields.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
     }
 
-    fn read(path: &str) -> Vec<Passport> {
-        let file = File::open(path).expect("cannot open file");
-        let buffer = BufReader::new(file);
-        let mut passports = vec![Passport::new()];
+    /// Every required field must be present, and every field must follow its rule
+    fn violations(&self, schema: &Schema) -> Vec<Violation> {
+        let mut violations: Vec<Violation> = schema.fields.iter()
+            .filter(|(key, field)| field.required && !self.fields.iter().any(|(k, _)| k == *key))
+            .map(|(key, _)| Violation::Missing { field: key.clone() })
+            .collect();
+
+        violations.extend(self.malformed.iter().map(|token| Violation::Malformed { token: token.clone() }));
+
+        let mut seen = HashSet::new();
+        for (key, value) in &self.fields {
+            if !seen.insert(key) {
+                violations.push(Violation::Duplicate { field: key.clone() });
+                continue;
+            }
+
+            let field = match schema.fields.get(key) {
+                Some(field) => field,
+                None if schema.allow_unknown => continue,
+                None => {
+                    violations.push(Violation::Unknown { field: key.clone() });
+                    continue;
+                }
+            };
 
-        for result in buffer.lines() {
-            let line = result.expect("cannot read line");
-            match line.as_str() {
-                "" => passports.push(Passport::new()),
-                text => passports.last_mut().unwrap().parse(text)
+            let problem = field.rule.as_ref().and_then(|rule| rule.check(value).err());
+            match problem {
+                Some(Problem::OutOfRange) =>
+                    violations.push(Violation::OutOfRange { field: key.clone(), value: value.clone() }),
+                Some(Problem::BadFormat) =>
+                    violations.push(Violation::BadFormat { field: key.clone(), value: value.clone() }),
+                None => {}
             }
         }
 
-        passports
+        violations
     }
 
-    fn parse(&mut self, text: &str) {
-        text.split(" ")
-            .for_each(|token| {
-                let mut parts = token.split(":");
-                let key = parts.next().expect("key not found").to_string();
-                let value = parts.next().expect("value not found").to_string();
-                self.0.insert(key, value);
-            })
+}
+
+impl Violation {
+
+    fn field(&self) -> &str {
+        match self {
+            Violation::Malformed { .. } => "",
+            Violation::Missing { field } => field,
+            Violation::Unknown { field } => field,
+            Violation::Duplicate { field } => field,
+            Violation::OutOfRange { field, .. } => field,
+            Violation::BadFormat { field, .. } => field
+        }
     }
 
-    fn is_valid(&self) -> bool {
-        let valid = self.0.iter()
-            .filter(|(key, value)| match key.as_str() {
-                    "byr" => Self::validate_range(value, 1920, 2002),
-                    "iyr" => Self::validate_range(value, 2010, 2020),
-                    "eyr" => Self::validate_range(value, 2020, 2030),
-                    "hgt" => Self::validate_height(value),
-                    "hcl" => Self::validate_hair_color(value),
-                    "ecl" => Self::validate_eye_color(value),
-                    "pid" => Self::validate_passport_id(value),
-                    "cid" => true,
-                    _ => false
-            })
-            .count();
+    fn value(&self) -> &str {
+        match self {
+            Violation::OutOfRange { value, .. } => value,
+            Violation::BadFormat { value, .. } => value,
+            Violation::Malformed { token } => token,
+            _ => ""
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Malformed { .. } => write!(f, "malformed"),
+            Violation::Missing { .. } => write!(f, "missing"),
+            Violation::Unknown { .. } => write!(f, "unknown field"),
+            Violation::Duplicate { .. } => write!(f, "duplicate"),
+            Violation::OutOfRange { .. } => write!(f, "out of range"),
+            Violation::BadFormat { .. } => write!(f, "bad format")
+        }
+    }
+}
+
+impl Report {
+
+    fn header(&self) {
+        match self {
+            Report::Table => println!("{:<8} {:<6} {:<6} {:<14} value", "record", "line", "field", "violation"),
+            Report::Json => print!("[")
+        }
+    }
+
+    /// Tables have one row for each violation, so valid passports don't appear in them.
+    /// JSON reports have one object for each passport.
+    fn passport(&self, passport: &Passport, violations: &[Violation]) {
+        match self {
+            Report::Table => for violation in violations {
+                println!(
+                    "{:<8} {:<6} {:<6} {:<14} {}",
+                    passport.record,
+                    passport.line,
+                    violation.field(),
+                    violation.to_string(),
+                    violation.value()
+                );
+            },
+            Report::Json => {
+                let diagnostic = Diagnostic {
+                    record: passport.record,
+                    line: passport.line,
+                    valid: violations.is_empty(),
+                    violations
+                };
+                let separator = if passport.record > 1 { "," } else { "" };
+                let json = serde_json::to_string(&diagnostic).expect("cannot serialize report");
+                print!("{}\n  {}", separator, json);
+            }
+        }
+    }
+
+    fn footer(&self) {
+        if let Report::Json = self {
+            println!("\n]");
+        }
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let path = args.next().expect("missing file path");
 
-        match valid {
-            8 => true,
-            7 => !self.0.contains_key("cid"),
-            _ => false
+    let mut schema = None;
+    let mut report = None;
+    let mut export = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schema" => schema = Some(args.next().expect("missing schema path")),
+            "--report" => report = match args.next().as_deref() {
+                Some("table") => Some(Report::Table),
+                Some("json") => Some(Report::Json),
+                _ => panic!("invalid report format")
+            },
+            "--export" => export = match args.next().as_deref() {
+                Some("csv") => Some(Export::Csv),
+                Some("jsonl") => Some(Export::JsonLines),
+                _ => panic!("invalid export format")
+            },
+            _ => panic!("invalid option")
         }
     }
 
-    fn validate_range(value: &str, min: u16, max: u16) -> bool {
-        value.parse::<u16>()
-            .map(|v| v >= min && v <= max)
-            .unwrap_or(false)
+    if report.is_some() && export.is_some() {
+        panic!("cannot both report and export");
     }
 
-    fn validate_height(value: &str) -> bool {
-        if value.len() < 3 {
-            return false;
+    let schema = match schema {
+        Some(path) => Schema::read(&path),
+        None => Schema::parse(schema::PASSPORT)
+    };
+    let schema = schema.unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+
+    let file = File::open(path).expect("cannot open file");
+    let records = Records::new(BufReader::new(file));
+
+    if let Some(report) = &report {
+        report.header();
+    }
+    if let Some(export) = &export {
+        export.header();
+    }
+
+    let mut valid = 0;
+    for record in records {
+        let record = record.unwrap_or_else(|error| {
+            eprintln!("cannot read passports: {}", error);
+            process::exit(1);
+        });
+
+        let passport = Passport::parse(&record);
+        let violations = passport.violations(&schema);
+        if let Some(report) = &report {
+            report.passport(&passport, &violations);
         }
+        if !violations.is_empty() {
+            continue;
+        }
+
+        valid += 1;
+        if let Some(export) = &export {
+            match ValidPassport::new(&passport) {
+                Some(passport) => export.passport(&passport),
+                None => {
+                    eprintln!("record {} is valid but its fields cannot be exported", passport.record);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    match (report, export) {
+        (Some(report), _) => report.footer(),
+        (None, Some(_)) => {},
+        (None, None) => println!("{}", valid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Record, starting line and violations of each passport in the text
+    fn check(text: &str) -> Vec<(usize, usize, Vec<Violation>)> {
+        check_with(text, &Schema::parse(schema::PASSPORT).expect("cannot parse schema"))
+    }
+
+    fn check_with(text: &str, schema: &Schema) -> Vec<(usize, usize, Vec<Violation>)> {
+        Records::new(text.as_bytes())
+            .map(|record| Passport::parse(&record.expect("cannot read record")))
+            .map(|passport| (passport.record, passport.line, passport.violations(schema)))
+            .collect()
+    }
+
+    fn out_of_range(field: &str, value: &str) -> Violation {
+        Violation::OutOfRange { field: field.to_string(), value: value.to_string() }
+    }
+
+    fn bad_format(field: &str, value: &str) -> Violation {
+        Violation::BadFormat { field: field.to_string(), value: value.to_string() }
+    }
+
+    #[test]
+    fn valid_passports() {
+        let passports = check(include_str!("../input/valid"));
+        assert_eq!(passports, vec![
+            (1, 1, vec![]),
+            (2, 4, vec![]),
+            (3, 7, vec![]),
+            (4, 12, vec![])
+        ]);
+    }
 
-        let split = value.len() - 2;
-        let number = &value[0..split];
-        let unit = &value[split..];
+    #[test]
+    fn invalid_passports() {
+        let passports = check(include_str!("../input/invalid"));
+        assert_eq!(passports, vec![
+            (1, 1, vec![
+                out_of_range("eyr", "1972"),
+                bad_format("hgt", "170"),
+                bad_format("pid", "186cm")
+            ]),
+            (2, 4, vec![
+                out_of_range("eyr", "1967")
+            ]),
+            (3, 8, vec![
+                bad_format("hcl", "dab227")
+            ]),
+            (4, 11, vec![
+                out_of_range("hgt", "59cm"),
+                out_of_range("ecl", "zzz"),
+                out_of_range("eyr", "2038"),
+                bad_format("hcl", "74454a"),
+                out_of_range("iyr", "2023"),
+                bad_format("pid", "3556412378"),
+                out_of_range("byr", "2007")
+            ])
+        ]);
+    }
+
+    #[test]
+    fn sample_passports() {
+        let passports = check(include_str!("../input/sample"));
+        let valid: Vec<bool> = passports.iter().map(|(_, _, violations)| violations.is_empty()).collect();
+        assert_eq!(valid, vec![true, false, true, false]);
+        assert_eq!(passports[1].2, vec![Violation::Missing { field: "hgt".to_string() }]);
+        assert_eq!(passports[3].2, vec![Violation::Missing { field: "byr".to_string() }]);
+    }
 
-        number.parse::<u8>()
-            .map(|n| match unit {
-                "cm" => HEIGHT_CM.contains(&n),
-                "in" => HEIGHT_IN.contains(&n),
-                _ => false
+    /// Fields of a valid passport, in any order, with or without a country
+    fn valid_fields() -> impl Strategy<Value = Vec<(String, String)>> {
+        let height = prop_oneof![
+            (150u32..=193).prop_map(|height| format!("{}cm", height)),
+            (59u32..=76).prop_map(|height| format!("{}in", height))
+        ];
+        let eyes = prop::sample::select(vec!["amb", "blu", "brn", "gry", "grn", "hzl", "oth"]);
+        let fields = (1920u32..=2002, 2010u32..=2020, 2020u32..=2030, height, "#[0-9a-f]{6}", eyes, "[0-9]{9}");
+        (fields, prop::option::of(1u32..1000))
+            .prop_map(|((byr, iyr, eyr, hgt, hcl, ecl, pid), cid)| {
+                let mut fields = vec![
+                    ("byr", byr.to_string()),
+                    ("iyr", iyr.to_string()),
+                    ("eyr", eyr.to_string()),
+                    ("hgt", hgt),
+                    ("hcl", hcl),
+                    ("ecl", ecl.to_string()),
+                    ("pid", pid)
+                ];
+                fields.extend(cid.map(|cid| ("cid", cid.to_string())));
+                fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect::<Vec<_>>()
             })
-            .unwrap_or(false)
+            .prop_shuffle()
+    }
+
+    /// Fields written on several lines, separated by spaces, tabs or line breaks
+    fn write(fields: &[(String, String)], separators: &[&str]) -> String {
+        let mut text = String::new();
+        for (i, (key, value)) in fields.iter().enumerate() {
+            if i > 0 {
+                text.push_str(separators[i % separators.len()]);
+            }
+            text.push_str(&format!("{}:{}", key, value));
+        }
+        text
+    }
+
+    fn separators() -> impl Strategy<Value = Vec<&'static str>> {
+        prop::collection::vec(prop::sample::select(vec![" ", "  ", "\t", "\n", "\r\n"]), 1..8)
     }
 
-    fn validate_hair_color(value: &str) -> bool {
-        if value.len() != 7 {
-            return false;
+    /// Values that break a rule, and whether they are out of range rather than badly written
+    const INVALID: &[(&str, &str, bool)] = &[
+        ("pid", "0123456789x", false),
+        ("pid", "a12345678", false),
+        ("hcl", "#623A2F", false),
+        ("byr", "+1980", false),
+        ("byr", "01980", false),
+        ("hgt", "+74in", false),
+        ("hgt", "74", false),
+        ("hgt", "77in", true),
+        ("eyr", "2031", true),
+        ("iyr", "2009", true),
+        ("ecl", "xyz", true)
+    ];
+
+    proptest! {
+        #[test]
+        fn generated_valid(fields in valid_fields(), separators in separators()) {
+            let passports = check(&write(&fields, &separators));
+            prop_assert_eq!(passports, vec![(1, 1, vec![])]);
+        }
+
+        #[test]
+        fn generated_invalid(mut fields in valid_fields(), separators in separators(), invalid in prop::sample::select(INVALID)) {
+            let (key, value, range) = invalid;
+            let field = fields.iter_mut().find(|(k, _)| k == key).expect("cannot find field");
+            field.1 = value.to_string();
+
+            let violation = match range {
+                true => out_of_range(key, value),
+                false => bad_format(key, value)
+            };
+            let passports = check(&write(&fields, &separators));
+            prop_assert_eq!(passports, vec![(1, 1, vec![violation])]);
         }
 
-        if &value[0..1] != "#" {
-            return false;
+        #[test]
+        fn generated_missing(mut fields in valid_fields(), separators in separators(), missing in "(byr|iyr|eyr|hgt|hcl|ecl|pid)") {
+            fields.retain(|(key, _)| *key != missing);
+            let passports = check(&write(&fields, &separators));
+            prop_assert_eq!(passports, vec![(1, 1, vec![Violation::Missing { field: missing }])]);
         }
 
-        value[1..].chars()
-            .all(|c| DIGITS.contains(&c) || LETTERS.contains(&c))
+        #[test]
+        fn generated_duplicate(mut fields in valid_fields(), separators in separators(), key in "(byr|cid)", value in "1900|1980|100") {
+            let present = fields.iter().any(|(k, _)| *k == key);
+            fields.push((key.clone(), value));
+            let passports = check(&write(&fields, &separators));
+
+            let expected = match !present {
+                // the only country is the added one, so it isn't a duplicate
+                true => vec![],
+                false => vec![Violation::Duplicate { field: key }]
+            };
+            prop_assert_eq!(passports, vec![(1, 1, expected)]);
+        }
     }
 
-    fn validate_eye_color(value: &str) -> bool {
-        EYE_COLORS.contains(&value)
+    #[test]
+    fn duplicates_are_reported_once_per_repeat() {
+        let text = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 cid:1\n\
+                    hcl:#623a2f byr:1900 cid:2 byr:1981";
+        assert_eq!(check(text), vec![(1, 1, vec![
+            Violation::Duplicate { field: "byr".to_string() },
+            Violation::Duplicate { field: "cid".to_string() },
+            Violation::Duplicate { field: "byr".to_string() }
+        ])]);
     }
 
-    fn validate_passport_id(value: &str) -> bool {
-        value.chars()
-            .filter(|c| DIGITS.contains(&c))
-            .count() == 9
+    #[test]
+    fn malformed_tokens() {
+        let text = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f oops";
+        assert_eq!(check(text), vec![(1, 1, vec![Violation::Malformed { token: "oops".to_string() }])]);
     }
 
-}
+    #[test]
+    fn unknown_fields() {
+        let text = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f cid:1 zip:12345";
+        assert_eq!(check(text), vec![(1, 1, vec![])]);
 
-fn main() {
-    let path = env::args().nth(1).expect("missing file path");
-    let passports = Passport::read(&path);
+        let mut schema = Schema::parse(schema::PASSPORT).expect("cannot parse schema");
+        schema.allow_unknown = false;
+        // cid is listed in the schema, without a rule
+        assert_eq!(check_with(text, &schema), vec![(1, 1, vec![
+            Violation::Unknown { field: "zip".to_string() }
+        ])]);
 
-    let valid = passports.iter()
-        .filter(|p| p.is_valid())
-        .count();
+        let schema = Schema::parse("[fields.pid]\nrequired = true\n").expect("cannot parse schema");
+        assert_eq!(check_with("pid:1 cid:2", &schema), vec![(1, 1, vec![
+            Violation::Unknown { field: "cid".to_string() }
+        ])]);
+    }
 
-    println!("{}", valid);
+    #[test]
+    fn strict_passports() {
+        let passports = check(include_str!("../input/strict"));
+        let violations: Vec<Vec<Violation>> = passports.into_iter().map(|(_, _, violations)| violations).collect();
+        assert_eq!(violations, vec![
+            vec![],
+            vec![bad_format("pid", "0123456789x")],
+            vec![bad_format("pid", "a12345678")],
+            vec![bad_format("hcl", "#623A2F")],
+            vec![bad_format("byr", "+1980")],
+            vec![bad_format("byr", "01980")],
+            vec![bad_format("hgt", "+74in")],
+            vec![Violation::Duplicate { field: "byr".to_string() }],
+            vec![Violation::Duplicate { field: "cid".to_string() }]
+        ]);
+    }
 }
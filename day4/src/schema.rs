@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+/// Schema used when none is given on the command line
+pub const PASSPORT: &str = include_str!("../schema/passport.toml");
+
+/// Fields of a document and the rules their values must follow
+#[derive(Debug, Deserialize)]
+pub struct Schema {
+    /// accept fields that aren't listed in the schema
+    #[serde(default)]
+    pub allow_unknown: bool,
+    pub fields: BTreeMap<String, Field>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Field {
+    #[serde(default)]
+    pub required: bool,
+    pub rule: Option<Rule>
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Rule {
+    /// integer between min and max, inclusive, written with exactly `digits` digits when set
+    Range { min: i64, max: i64, digits: Option<usize> },
+    /// integer followed by a unit, like `183cm`, with a range for each unit
+    Units { units: BTreeMap<String, (i64, i64)> },
+    Regex {
+        #[serde(deserialize_with = "deserialize_regex")]
+        pattern: Regex
+    },
+    Enum { values: Vec<String> }
+}
+
+/// Why a value doesn't follow a rule
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    /// the value can be read, but isn't one of the accepted values
+    OutOfRange,
+    /// the value isn't written the way the rule expects
+    BadFormat
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error)
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::Io(error) => write!(f, "cannot read schema: {}", error),
+            SchemaError::Toml(error) => write!(f, "invalid schema: {}", error),
+            SchemaError::Json(error) => write!(f, "invalid schema: {}", error)
+        }
+    }
+}
+
+impl Schema {
+
+    /// Load a schema written in TOML, or in JSON when the file ends with `.json`
+    pub fn read(path: &str) -> Result<Schema, SchemaError> {
+        let text = fs::read_to_string(path).map_err(SchemaError::Io)?;
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&text).map_err(SchemaError::Json),
+            _ => Self::parse(&text)
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Schema, SchemaError> {
+        toml::from_str(text).map_err(SchemaError::Toml)
+    }
+}
+
+impl Rule {
+
+    pub fn check(&self, value: &str) -> Result<(), Problem> {
+        match self {
+            Rule::Range { min, max, digits } => {
+                if digits.is_some_and(|digits| value.len() != digits) {
+                    return Err(Problem::BadFormat);
+                }
+                let number = Self::integer(value)?;
+                Self::within(number, *min, *max)
+            },
+            Rule::Units { units } => {
+                let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+                let (number, unit) = value.split_at(split);
+                let number = Self::integer(number)?;
+                let (min, max) = units.get(unit).ok_or(Problem::BadFormat)?;
+                Self::within(number, *min, *max)
+            },
+            Rule::Regex { pattern } => match pattern.is_match(value) {
+                true => Ok(()),
+                false => Err(Problem::BadFormat)
+            },
+            Rule::Enum { values } => match values.iter().any(|v| v == value) {
+                true => Ok(()),
+                false => Err(Problem::OutOfRange)
+            }
+        }
+    }
+
+    /// Only plain digits are accepted, `str::parse` would also take a sign like `+2002`
+    fn integer(text: &str) -> Result<i64, Problem> {
+        match !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit()) {
+            true => text.parse().map_err(|_| Problem::BadFormat),
+            false => Err(Problem::BadFormat)
+        }
+    }
+
+    fn within(number: i64, min: i64, max: i64) -> Result<(), Problem> {
+        match number >= min && number <= max {
+            true => Ok(()),
+            false => Err(Problem::OutOfRange)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn check(field: &str, value: &str) -> Result<(), Problem> {
+        let schema = Schema::parse(PASSPORT).expect("cannot parse schema");
+        let rule = schema.fields[field].rule.as_ref().expect("field has no rule");
+        rule.check(value)
+    }
+
+    #[test]
+    fn written_differently() {
+        assert_eq!(check("pid", "087499704"), Ok(()));
+        assert_eq!(check("pid", "0123456789x"), Err(Problem::BadFormat));
+        assert_eq!(check("pid", "a12345678"), Err(Problem::BadFormat));
+        assert_eq!(check("hcl", "#623a2f"), Ok(()));
+        assert_eq!(check("hcl", "#623A2F"), Err(Problem::BadFormat));
+        assert_eq!(check("byr", "1980"), Ok(()));
+        assert_eq!(check("byr", "+1980"), Err(Problem::BadFormat));
+        assert_eq!(check("byr", "01980"), Err(Problem::BadFormat));
+        assert_eq!(check("byr", ""), Err(Problem::BadFormat));
+        assert_eq!(check("hgt", "74in"), Ok(()));
+        assert_eq!(check("hgt", "+74in"), Err(Problem::BadFormat));
+        assert_eq!(check("hgt", "74"), Err(Problem::BadFormat));
+        assert_eq!(check("hgt", "in"), Err(Problem::BadFormat));
+    }
+
+    #[test]
+    fn outside_of_the_rule() {
+        assert_eq!(check("byr", "1919"), Err(Problem::OutOfRange));
+        assert_eq!(check("byr", "2003"), Err(Problem::OutOfRange));
+        assert_eq!(check("hgt", "58in"), Err(Problem::OutOfRange));
+        assert_eq!(check("hgt", "194cm"), Err(Problem::OutOfRange));
+        assert_eq!(check("ecl", "zzz"), Err(Problem::OutOfRange));
+    }
+
+    #[test]
+    fn schema_in_json() {
+        let schema: Schema = serde_json::from_str(r#"{
+            "fields": { "age": { "required": true, "rule": { "type": "range", "min": 0, "max": 150 } } }
+        }"#).expect("cannot parse schema");
+        assert!(!schema.allow_unknown);
+        let rule = schema.fields["age"].rule.as_ref().unwrap();
+        assert_eq!(rule.check("42"), Ok(()));
+        assert_eq!(rule.check("151"), Err(Problem::OutOfRange));
+    }
+
+    proptest! {
+        #[test]
+        fn years(year in 0u32..10000) {
+            let expected = match (1920..=2002).contains(&year) {
+                true => Ok(()),
+                false => Err(Problem::OutOfRange)
+            };
+            prop_assert_eq!(check("byr", &format!("{:04}", year)), expected);
+            prop_assert_eq!(check("byr", &format!("+{:04}", year)), Err(Problem::BadFormat));
+            prop_assert_eq!(check("byr", &format!("0{:04}", year)), Err(Problem::BadFormat));
+        }
+
+        #[test]
+        fn heights(height in 0u32..300, unit in "(cm|in|mm|)") {
+            let range = match unit.as_str() {
+                "cm" => Some(150..=193),
+                "in" => Some(59..=76),
+                _ => None
+            };
+            let expected = match range {
+                Some(range) if range.contains(&height) => Ok(()),
+                Some(_) => Err(Problem::OutOfRange),
+                None => Err(Problem::BadFormat)
+            };
+            prop_assert_eq!(check("hgt", &format!("{}{}", height, unit)), expected);
+            prop_assert_eq!(check("hgt", &format!("-{}{}", height, unit)), Err(Problem::BadFormat));
+        }
+
+        #[test]
+        fn hair_colors(color in "#[0-9a-f]{6}", wrong in "#[0-9a-f]{0,5}[A-Zg-z#][0-9a-f]{0,5}") {
+            prop_assert_eq!(check("hcl", &color), Ok(()));
+            prop_assert_eq!(check("hcl", &wrong), Err(Problem::BadFormat));
+        }
+
+        #[test]
+        fn passport_ids(id in "[0-9]{9}", short in "[0-9]{0,8}", long in "[0-9]{10,12}", letter in "[0-9]{0,8}[a-z][0-9]{0,8}") {
+            prop_assert_eq!(check("pid", &id), Ok(()));
+            prop_assert_eq!(check("pid", &short), Err(Problem::BadFormat));
+            prop_assert_eq!(check("pid", &long), Err(Problem::BadFormat));
+            prop_assert_eq!(check("pid", &letter), Err(Problem::BadFormat));
+        }
+    }
+}
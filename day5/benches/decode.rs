@@ -0,0 +1,50 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use day5::{Layout, Seat};
+use xorshift::XorShift;
+
+/// Number of boarding passes decoded in each iteration
+const PASSES: usize = 2_000_000;
+
+/// Boarding passes of random seats spread around the plane
+fn passes(layout: &Layout) -> Vec<String> {
+    let mut random = XorShift::default();
+    let seats = (layout.rows * layout.columns) as u64;
+    (0..PASSES)
+        .map(|_| {
+            let (row, column) = layout.position(random.below(seats) as u32).expect("seat id outside of the plane");
+            layout.encode(row, column).expect("seat outside of the plane")
+        })
+        .collect()
+}
+
+/// Compare the decoder following the splits of each letter against the one reading the pass as bits
+fn decode(c: &mut Criterion) {
+    for name in ["128x8", "100x6"] {
+        let layout = Layout::parse(name);
+        let passes = passes(&layout);
+
+        let mut group = c.benchmark_group(format!("decode {}", name));
+        // each iteration goes through all the passes, so a few samples are enough
+        group.sample_size(10);
+        group.throughput(Throughput::Elements(PASSES as u64));
+        group.bench_function("splits", |b| b.iter(|| {
+            passes.iter()
+                .map(|pass| {
+                    let seat = Seat::parse(black_box(pass), &layout).expect("invalid boarding pass");
+                    seat.seat_id(&layout).expect("invalid boarding pass") as u64
+                })
+                .sum::<u64>()
+        }));
+        group.bench_function("bits", |b| b.iter(|| {
+            passes.iter()
+                .map(|pass| layout.decode(black_box(pass)).expect("invalid boarding pass") as u64)
+                .sum::<u64>()
+        }));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
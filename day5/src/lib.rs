@@ -0,0 +1,3 @@
+mod seat;
+
+pub use seat::{Bounds, Layout, PassErrorKind, Seat};
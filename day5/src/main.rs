@@ -1,101 +1,128 @@
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::process;
 
-const ROWS: (u32, u32) = (0, 127);
-const COLUMNS: (u32, u32) = (0, 7);
+mod occupancy;
 
-#[derive(Debug)]
-enum Split {
-    Bottom,
-    Top
-}
+use day5::{Bounds, Layout, PassErrorKind};
+use occupancy::Occupancy;
 
-#[derive(Debug)]
-struct Seat {
-    rows: Vec<Split>,
-    columns: Vec<Split>
+/// What to do once the arguments are read
+enum Mode {
+    Decode(String),
+    Encode(Bounds),
+    EncodeId(u32)
 }
 
-type Bounds = (u32, u32);
+/// How to output the occupancy of the plane
+enum Report {
+    Text,
+    Json
+}
 
-impl Split {
+#[derive(Debug)]
+struct PassError {
+    line: usize,
+    kind: PassErrorKind
+}
 
-    fn reduce(&self, bounds: &Bounds) -> Bounds {
-        let remaining = (bounds.1 - bounds.0) / 2;
-        match self {
-            Split::Bottom => (bounds.0, bounds.0 + remaining),
-            Split::Top => (bounds.1 - remaining, bounds.1)
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            PassErrorKind::WrongLength { expected, found } =>
+                write!(f, "expected {} letters, found {}", expected, found),
+            PassErrorKind::Interleaved { column, symbol } =>
+                write!(f, "column {}: {:?} is out of place", column, symbol),
+            PassErrorKind::InvalidSymbol { column, symbol } =>
+                write!(f, "column {}: invalid symbol {:?}", column, symbol),
+            PassErrorKind::OutsidePlane { row, column } =>
+                write!(f, "row {}, column {} is outside of the plane", row, column)
         }
     }
 }
 
-impl Seat {
-
-    fn parse(text: String) -> Seat {
-        let rows = text.chars()
-            .filter_map(|c| match c {
-                'F' => Some(Split::Bottom),
-                'B' => Some(Split::Top),
-                _ => None
-            })
-            .collect();
-
-        let columns = text.chars()
-            .filter_map(|c| match c {
-                'L' => Some(Split::Bottom),
-                'R' => Some(Split::Top),
-                _ => None
-            })
-            .collect();
-
-        Seat {
-            rows: rows,
-            columns: columns
+fn parse_position(text: &str) -> Bounds {
+    let mut parts = text.split(',');
+    let row = parts.next().expect("missing row").parse().expect("cannot parse row");
+    let column = parts.next().expect("missing column").parse().expect("cannot parse column");
+    (row, column)
+}
+
+fn main() {
+    let mut layout = Layout::new();
+    let mut mode = None;
+    let mut report = None;
+    let mut lenient = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--layout" => layout = Layout::parse(&args.next().expect("missing layout")),
+            "--encode" => mode = Some(Mode::Encode(parse_position(&args.next().expect("missing seat position")))),
+            "--encode-id" => {
+                let seat_id = args.next().expect("missing seat id").parse().expect("cannot parse seat id");
+                mode = Some(Mode::EncodeId(seat_id));
+            },
+            "--report" => report = Some(Report::Text),
+            "--json" => report = Some(Report::Json),
+            "--lenient" => lenient = true,
+            _ => mode = Some(Mode::Decode(arg))
         }
     }
 
-    fn seat_id(&self) -> u32 {
-        let bounds = self.calculate();
-        bounds.0 * 8 + bounds.1
-    }
+    let path = match mode.expect("no path to input file") {
+        Mode::Decode(path) => path,
+        Mode::Encode((row, column)) => {
+            println!("{}", layout.encode(row, column).expect("seat outside of the plane"));
+            return;
+        },
+        Mode::EncodeId(seat_id) => {
+            let (row, column) = layout.position(seat_id).expect("seat id outside of the plane");
+            println!("{}", layout.encode(row, column).expect("seat outside of the plane"));
+            return;
+        }
+    };
 
-    fn calculate(&self) -> Bounds {
-        let row = Self::reduce(&self.rows, ROWS);
-        let column = Self::reduce(&self.columns, COLUMNS);
-        (row, column)
-    }
+    let file = File::open(path).expect("cannot open file");
+    let buffer = BufReader::new(file);
 
-    fn reduce(splits: &Vec<Split>, value: Bounds) -> u32 {
-        let mut value = value.clone();
-        for split in splits {
-            value = split.reduce(&value);
+    let mut seat_ids = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in buffer.lines().enumerate() {
+        let line = line.expect("cannot read line");
+        match layout.decode(&line) {
+            Ok(seat_id) => seat_ids.push(seat_id),
+            Err(kind) if lenient => errors.push(PassError { line: i + 1, kind }),
+            Err(kind) => {
+                eprintln!("{}", PassError { line: i + 1, kind });
+                process::exit(1);
+            }
         }
+    }
 
-        match splits.last() {
-            Some(Split::Bottom) => value.0,
-            Some(Split::Top) => value.1,
-            _ => panic!("no splits in list")
+    if !errors.is_empty() {
+        eprintln!("skipped {} invalid boarding passes", errors.len());
+        for error in errors {
+            eprintln!("{}", error);
         }
     }
 
-}
-
-fn main() {
-    let path = env::args().nth(1).expect("no path to input file");
-    let file = File::open(path).expect("cannot open file");
-    let buffer = BufReader::new(file);
-
-    let mut seat_ids: Vec<u32> = buffer.lines()
-        .map(|result| result.expect("cannot read line"))
-        .map(Seat::parse)
-        .map(|s| s.seat_id())
-        .collect();
+    if let Some(report) = report {
+        let occupancy = Occupancy::new(layout, &seat_ids);
+        match report {
+            Report::Text => occupancy.print_text(),
+            Report::Json => occupancy.print_json()
+        }
+        return;
+    }
 
     seat_ids.sort();
-    
-    println!("highest seat id {}", seat_ids.last().unwrap());
+
+    println!("highest seat id {}", seat_ids.last().expect("no boarding passes"));
 
     for (i, seat_id) in seat_ids[1..].iter().enumerate() {
         let previous = seat_ids[i];
@@ -104,3 +131,4 @@ fn main() {
         }
     }
 }
+
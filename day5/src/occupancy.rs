@@ -0,0 +1,209 @@
+use crate::Layout;
+
+/// Number of boarding passes found for each seat of the plane
+pub struct Occupancy {
+    layout: Layout,
+    counts: Vec<u32>
+}
+
+impl Occupancy {
+
+    pub fn new(layout: Layout, seat_ids: &[u32]) -> Occupancy {
+        let mut counts = vec![0; (layout.rows * layout.columns) as usize];
+        for seat_id in seat_ids {
+            counts[*seat_id as usize] += 1;
+        }
+
+        Occupancy { layout, counts }
+    }
+
+    fn taken(&self, seat_id: u32) -> bool {
+        self.counts[seat_id as usize] > 0
+    }
+
+    /// Seats with more than one pass, with their number of passes
+    pub fn duplicates(&self) -> Vec<(u32, u32)> {
+        self.counts.iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 1)
+            .map(|(seat_id, count)| (seat_id as u32, *count))
+            .collect()
+    }
+
+    /// Runs of free seats, as inclusive ranges of seat ids
+    pub fn missing(&self) -> Vec<(u32, u32)> {
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for seat_id in 0..self.counts.len() as u32 {
+            if self.taken(seat_id) {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(range) if range.1 + 1 == seat_id => range.1 = seat_id,
+                _ => ranges.push((seat_id, seat_id))
+            }
+        }
+        ranges
+    }
+
+    /// Free seats whose ids on both sides are taken, which is where our own seat can be
+    pub fn candidates(&self) -> Vec<u32> {
+        (1..self.counts.len().saturating_sub(1) as u32)
+            .filter(|seat_id| !self.taken(*seat_id) && self.taken(seat_id - 1) && self.taken(seat_id + 1))
+            .collect()
+    }
+
+    /// One line per row, `.` for a free seat, `#` for a taken seat, and the number of passes
+    /// when a seat is on more than one of them
+    pub fn grid(&self) -> String {
+        let mut grid = String::new();
+        for row in 0..self.layout.rows {
+            grid.push_str(&format!("{:>4} ", row));
+            for column in 0..self.layout.columns {
+                let symbol = match self.counts[self.layout.seat_id(row, column) as usize] {
+                    0 => '.',
+                    1 => '#',
+                    count @ 2..=9 => char::from_digit(count, 10).unwrap(),
+                    _ => '+'
+                };
+                grid.push(symbol);
+            }
+            grid.push('\n');
+        }
+        grid
+    }
+
+    pub fn print_text(&self) {
+        print!("{}", self.grid());
+
+        let duplicates = self.duplicates();
+        println!("{} duplicate seats", duplicates.len());
+        for (seat_id, count) in duplicates {
+            let (row, column) = self.layout.position(seat_id).unwrap();
+            println!("  seat id {} (row {}, column {}) on {} passes", seat_id, row, column, count);
+        }
+
+        let missing = self.missing();
+        println!("{} missing ranges", missing.len());
+        for (first, last) in missing {
+            match first == last {
+                true => println!("  seat id {}", first),
+                false => println!("  seat ids {} to {}, {} seats", first, last, last - first + 1)
+            }
+        }
+
+        let candidates = self.candidates();
+        println!("{} candidate seats", candidates.len());
+        for seat_id in candidates {
+            let (row, column) = self.layout.position(seat_id).unwrap();
+            println!("  seat id {} (row {}, column {})", seat_id, row, column);
+        }
+    }
+
+    pub fn print_json(&self) {
+        print!("{}", self.json());
+    }
+
+    fn json(&self) -> String {
+        let list = |items: Vec<String>| format!("[{}]", items.join(", "));
+
+        let grid: Vec<String> = (0..self.layout.rows)
+            .map(|row| {
+                let counts = (0..self.layout.columns)
+                    .map(|column| self.counts[self.layout.seat_id(row, column) as usize].to_string())
+                    .collect();
+                list(counts)
+            })
+            .collect();
+
+        let duplicates = self.duplicates().iter()
+            .map(|(seat_id, count)| format!("{{\"seat_id\": {}, \"passes\": {}}}", seat_id, count))
+            .collect();
+
+        let missing = self.missing().iter()
+            .map(|(first, last)| format!("{{\"first\": {}, \"last\": {}}}", first, last))
+            .collect();
+
+        let candidates = self.candidates().iter()
+            .map(|seat_id| seat_id.to_string())
+            .collect();
+
+        let mut json = String::new();
+        json.push_str("{\n");
+        json.push_str(&format!("  \"rows\": {},\n", self.layout.rows));
+        json.push_str(&format!("  \"columns\": {},\n", self.layout.columns));
+        json.push_str(&format!("  \"grid\": [\n    {}\n  ],\n", grid.join(",\n    ")));
+        json.push_str(&format!("  \"duplicates\": {},\n", list(duplicates)));
+        json.push_str(&format!("  \"missing\": {},\n", list(missing)));
+        json.push_str(&format!("  \"candidates\": {}\n", list(candidates)));
+        json.push_str("}\n");
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4x3 plane: seats 0 and 1 are free at the front, 11 at the back, and 4, 7 and 8 in the middle
+    fn occupancy() -> Occupancy {
+        Occupancy::new(Layout::parse("4x3"), &[2, 3, 5, 5, 6, 9, 10, 10, 10, 3])
+    }
+
+    #[test]
+    fn duplicates() {
+        assert_eq!(occupancy().duplicates(), vec![(3, 2), (5, 2), (10, 3)]);
+    }
+
+    #[test]
+    fn missing_runs_include_the_front_and_back() {
+        assert_eq!(occupancy().missing(), vec![(0, 1), (4, 4), (7, 8), (11, 11)]);
+    }
+
+    #[test]
+    fn candidates_need_taken_seats_on_both_sides() {
+        assert_eq!(occupancy().candidates(), vec![4]);
+
+        // the first and last seats are never candidates, even with a taken neighbour
+        let occupancy = Occupancy::new(Layout::parse("1x5"), &[1, 3]);
+        assert_eq!(occupancy.candidates(), vec![2]);
+        assert_eq!(occupancy.missing(), vec![(0, 0), (2, 2), (4, 4)]);
+    }
+
+    #[test]
+    fn full_and_empty_planes() {
+        let full = Occupancy::new(Layout::parse("2x2"), &[0, 1, 2, 3]);
+        assert!(full.duplicates().is_empty());
+        assert!(full.missing().is_empty());
+        assert!(full.candidates().is_empty());
+
+        let empty = Occupancy::new(Layout::parse("2x2"), &[]);
+        assert_eq!(empty.missing(), vec![(0, 3)]);
+        assert!(empty.candidates().is_empty());
+    }
+
+    #[test]
+    fn grid() {
+        assert_eq!(occupancy().grid(), "   0 ..#\n   1 2.2\n   2 #..\n   3 #3.\n");
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(occupancy().json(), concat!(
+            "{\n",
+            "  \"rows\": 4,\n",
+            "  \"columns\": 3,\n",
+            "  \"grid\": [\n",
+            "    [0, 0, 1],\n",
+            "    [2, 0, 2],\n",
+            "    [1, 0, 0],\n",
+            "    [1, 3, 0]\n",
+            "  ],\n",
+            "  \"duplicates\": [{\"seat_id\": 3, \"passes\": 2}, {\"seat_id\": 5, \"passes\": 2}, ",
+            "{\"seat_id\": 10, \"passes\": 3}],\n",
+            "  \"missing\": [{\"first\": 0, \"last\": 1}, {\"first\": 4, \"last\": 4}, ",
+            "{\"first\": 7, \"last\": 8}, {\"first\": 11, \"last\": 11}],\n",
+            "  \"candidates\": [4]\n",
+            "}\n"
+        ));
+    }
+}
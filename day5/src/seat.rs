@@ -0,0 +1,317 @@
+/// Number of rows and columns of seats in the plane
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub rows: u32,
+    pub columns: u32
+}
+
+/// Why a boarding pass cannot be decoded. Columns start at 1.
+#[derive(Debug, PartialEq)]
+pub enum PassErrorKind {
+    WrongLength { expected: usize, found: usize },
+    /// a row letter among the column letters, or the other way around
+    Interleaved { column: usize, symbol: char },
+    InvalidSymbol { column: usize, symbol: char },
+    /// the letters point to a row or column past the end of a layout that isn't a power of two
+    OutsidePlane { row: u32, column: u32 }
+}
+
+#[derive(Debug)]
+enum Split {
+    Bottom,
+    Top
+}
+
+#[derive(Debug)]
+pub struct Seat {
+    rows: Vec<Split>,
+    columns: Vec<Split>
+}
+
+pub type Bounds = (u32, u32);
+
+impl Layout {
+
+    /// Layout of the plane in the puzzle
+    pub fn new() -> Layout {
+        Layout {
+            rows: 128,
+            columns: 8
+        }
+    }
+
+    /// Parse a layout written as `<rows>x<columns>`. Every seat must have a seat id that fits in a u32.
+    pub fn parse(text: &str) -> Layout {
+        let mut parts = text.split('x');
+        let rows: u32 = parts.next().expect("missing rows").parse().expect("cannot parse rows");
+        let columns: u32 = parts.next().expect("missing columns").parse().expect("cannot parse columns");
+        if rows == 0 || columns == 0 {
+            panic!("layout must have at least one row and one column");
+        }
+        if rows.checked_mul(columns).is_none() {
+            panic!("layout has too many seats to number them");
+        }
+
+        Layout { rows, columns }
+    }
+
+    /// Number of letters needed to tell `count` seats apart. When `count` isn't a power of two,
+    /// the last splits can point past the plane, and such passes are rejected when decoding.
+    fn bits(count: u32) -> u32 {
+        u32::BITS - (count - 1).leading_zeros()
+    }
+
+    pub fn row_bits(&self) -> u32 {
+        Self::bits(self.rows)
+    }
+
+    pub fn column_bits(&self) -> u32 {
+        Self::bits(self.columns)
+    }
+
+    pub fn seat_id(&self, row: u32, column: u32) -> u32 {
+        row * self.columns + column
+    }
+
+    /// Row and column of a seat id
+    pub fn position(&self, seat_id: u32) -> Option<Bounds> {
+        let position = (seat_id / self.columns, seat_id % self.columns);
+        match position.0 < self.rows {
+            true => Some(position),
+            false => None
+        }
+    }
+
+    /// Write the boarding pass of a seat, like `FBFBBFFRLR`
+    pub fn encode(&self, row: u32, column: u32) -> Option<String> {
+        if row >= self.rows || column >= self.columns {
+            return None;
+        }
+
+        let letters = |value: u32, bits: u32, bottom: char, top: char| {
+            (0..bits).rev()
+                .map(move |bit| match (value >> bit) & 1 {
+                    0 => bottom,
+                    _ => top
+                })
+        };
+
+        let pass = letters(row, self.row_bits(), 'F', 'B')
+            .chain(letters(column, self.column_bits(), 'L', 'R'))
+            .collect();
+
+        Some(pass)
+    }
+
+    /// Check that the pass has as many letters as the layout needs, with the row letters first
+    pub fn validate(&self, pass: &str) -> Result<(), PassErrorKind> {
+        let row_bits = self.row_bits() as usize;
+        let expected = row_bits + self.column_bits() as usize;
+        let found = pass.chars().count();
+        if found != expected {
+            return Err(PassErrorKind::WrongLength { expected, found });
+        }
+
+        for (i, symbol) in pass.chars().enumerate() {
+            let column = i + 1;
+            match (symbol, i < row_bits) {
+                ('F' | 'B', true) | ('L' | 'R', false) => {},
+                ('F' | 'B', false) | ('L' | 'R', true) =>
+                    return Err(PassErrorKind::Interleaved { column, symbol }),
+                _ => return Err(PassErrorKind::InvalidSymbol { column, symbol })
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the pass as two binary numbers, F and L being 0, B and R being 1, and return the seat id.
+    /// Passes of the wrong length, with letters out of place or pointing past the plane are rejected.
+    pub fn decode(&self, pass: &str) -> Result<u32, PassErrorKind> {
+        let bytes = pass.as_bytes();
+        let row_bits = self.row_bits() as usize;
+        if bytes.len() != row_bits + self.column_bits() as usize {
+            return Err(self.validate(pass).expect_err("pass of the wrong length"));
+        }
+
+        // F and L have their third bit set, B and R don't. Letters are checked all at once at the end
+        // instead of branching on each of them, random passes would make those branches unpredictable.
+        let bit = |byte: u8| (!byte >> 2 & 1) as u32;
+        let mut valid = true;
+
+        let mut row = 0;
+        for &byte in &bytes[..row_bits] {
+            valid &= (byte == b'F') | (byte == b'B');
+            row = row << 1 | bit(byte);
+        }
+
+        let mut column = 0;
+        for &byte in &bytes[row_bits..] {
+            valid &= (byte == b'L') | (byte == b'R');
+            column = column << 1 | bit(byte);
+        }
+
+        // finding out what is wrong is slower, but only happens for invalid passes
+        if !valid {
+            return Err(self.validate(pass).expect_err("pass with invalid letters"));
+        }
+
+        match row < self.rows && column < self.columns {
+            true => Ok(self.seat_id(row, column)),
+            false => Err(PassErrorKind::OutsidePlane { row, column })
+        }
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout::new()
+    }
+}
+
+impl Split {
+
+    /// Keep the lower or upper half of the bounds. Bounds always hold a power of two of seats.
+    fn reduce(&self, bounds: &Bounds) -> Bounds {
+        let size = bounds.1 - bounds.0 + 1;
+        let half = size / 2;
+        match self {
+            Split::Bottom => (bounds.0, bounds.0 + half - 1),
+            Split::Top => (bounds.0 + half, bounds.1)
+        }
+    }
+}
+
+impl Seat {
+
+    pub fn parse(text: &str, layout: &Layout) -> Result<Seat, PassErrorKind> {
+        layout.validate(text)?;
+
+        let split = |c| match c {
+            'F' | 'L' => Split::Bottom,
+            _ => Split::Top
+        };
+        let row_bits = layout.row_bits() as usize;
+
+        Ok(Seat {
+            rows: text.chars().take(row_bits).map(split).collect(),
+            columns: text.chars().skip(row_bits).map(split).collect()
+        })
+    }
+
+    pub fn seat_id(&self, layout: &Layout) -> Result<u32, PassErrorKind> {
+        let (row, column) = self.calculate(layout)?;
+        Ok(layout.seat_id(row, column))
+    }
+
+    pub fn calculate(&self, layout: &Layout) -> Result<Bounds, PassErrorKind> {
+        let row = Self::reduce(&self.rows, layout.row_bits());
+        let column = Self::reduce(&self.columns, layout.column_bits());
+        match row < layout.rows && column < layout.columns {
+            true => Ok((row, column)),
+            false => Err(PassErrorKind::OutsidePlane { row, column })
+        }
+    }
+
+    /// Follow the splits down from all the seats that `bits` letters can tell apart.
+    /// Parsing made sure there is one split for each bit.
+    fn reduce(splits: &[Split], bits: u32) -> u32 {
+        let mut value = (0, (1 << bits) - 1);
+        for split in splits {
+            value = split.reduce(&value);
+        }
+        value.0
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode then decode every seat of the layout with both decoders
+    fn roundtrip(layout: &Layout) {
+        for seat_id in 0..layout.rows * layout.columns {
+            let (row, column) = layout.position(seat_id).expect("seat id outside of the plane");
+            let pass = layout.encode(row, column).expect("seat outside of the plane");
+            assert_eq!(pass.len(), (layout.row_bits() + layout.column_bits()) as usize);
+
+            let seat = Seat::parse(&pass, layout).and_then(|seat| seat.calculate(layout));
+            assert_eq!(seat, Ok((row, column)), "pass {}", pass);
+            assert_eq!(layout.decode(&pass), Ok(seat_id), "pass {}", pass);
+        }
+    }
+
+    #[test]
+    fn roundtrip_puzzle_layout() {
+        roundtrip(&Layout::new());
+    }
+
+    #[test]
+    fn roundtrip_other_layouts() {
+        for layout in ["100x6", "1x1", "1x8", "128x1", "3x5", "1000x10"] {
+            roundtrip(&Layout::parse(layout));
+        }
+    }
+
+    #[test]
+    fn puzzle_examples() {
+        let layout = Layout::new();
+        for (pass, seat_id) in [("FBFBBFFRLR", 357), ("BFFFBBFRRR", 567), ("FFFBBBFRRR", 119), ("BBFFBBFRLL", 820)] {
+            assert_eq!(layout.decode(pass), Ok(seat_id));
+            assert_eq!(Seat::parse(pass, &layout).and_then(|seat| seat.seat_id(&layout)), Ok(seat_id));
+        }
+        assert_eq!(layout.encode(44, 5).as_deref(), Some("FBFBBFFRLR"));
+    }
+
+    #[test]
+    fn outside_plane() {
+        let layout = Layout::parse("100x6");
+        assert_eq!(layout.encode(100, 0), None);
+        assert_eq!(layout.encode(0, 6), None);
+        assert_eq!(layout.position(600), None);
+        assert_eq!(layout.decode("BBBBBBBLLL"), Err(PassErrorKind::OutsidePlane { row: 127, column: 0 }));
+        assert_eq!(layout.decode("FFFFFFFRRR"), Err(PassErrorKind::OutsidePlane { row: 0, column: 7 }));
+    }
+
+    #[test]
+    fn largest_layout() {
+        let layout = Layout::parse("65536x65535");
+        assert_eq!(layout.seat_id(65535, 65534), 65536 * 65535 - 1);
+        assert_eq!(layout.decode(&layout.encode(65535, 65534).unwrap()), Ok(65536 * 65535 - 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "layout has too many seats")]
+    fn rejects_layouts_with_too_many_seats() {
+        Layout::parse("100000x100000");
+    }
+
+    #[test]
+    #[should_panic(expected = "layout has too many seats")]
+    fn rejects_layouts_one_seat_too_large() {
+        Layout::parse("65536x65536");
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one row and one column")]
+    fn rejects_empty_layouts() {
+        Layout::parse("0x8");
+    }
+
+    #[test]
+    fn invalid_passes() {
+        let layout = Layout::new();
+        let errors = [
+            ("FBFBBFFRL", PassErrorKind::WrongLength { expected: 10, found: 9 }),
+            ("FBFBBFFRLRL", PassErrorKind::WrongLength { expected: 10, found: 11 }),
+            ("FBFBBFRRLR", PassErrorKind::Interleaved { column: 7, symbol: 'R' }),
+            ("FBFBBFFRLB", PassErrorKind::Interleaved { column: 10, symbol: 'B' }),
+            ("FBFXBFFRLR", PassErrorKind::InvalidSymbol { column: 4, symbol: 'X' }),
+            ("FBFBBFFRLr", PassErrorKind::InvalidSymbol { column: 10, symbol: 'r' })
+        ];
+        for (pass, error) in errors {
+            assert_eq!(layout.decode(pass), Err(error), "pass {}", pass);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use day6::Answers;
+use xorshift::XorShift;
+
+/// Number of groups in each iteration
+const GROUPS: usize = 1_000_000;
+
+/// Random groups of one to five people, each answering some questions of the alphabet
+fn groups(alphabet: &[char]) -> Vec<Vec<String>> {
+    let mut random = XorShift::default();
+    (0..GROUPS)
+        .map(|_| {
+            (0..1 + random.below(5))
+                .map(|_| {
+                    let answers = 1 + random.below((1 << alphabet.len()) - 1);
+                    alphabet.iter()
+                        .enumerate()
+                        .filter(|(bit, _)| answers & (1 << bit) != 0)
+                        .map(|(_, question)| *question)
+                        .collect()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Questions answered by anyone and by everyone, summed over all the groups
+fn hash_sets(groups: &[Vec<HashSet<char>>]) -> (usize, usize) {
+    let (mut total, mut common) = (0, 0);
+    for group in groups {
+        total += group.iter()
+            .fold(HashSet::new(), |total, person| total.union(person).cloned().collect())
+            .len();
+        common += group[1..].iter()
+            .fold(group[0].clone(), |total, person| total.intersection(person).cloned().collect())
+            .len();
+    }
+    (total, common)
+}
+
+fn bitsets(groups: &[Vec<Answers>]) -> (usize, usize) {
+    let (mut total, mut common) = (0, 0);
+    for group in groups {
+        total += group.iter()
+            .fold(Answers::new(), |total, person| total.union(person))
+            .len();
+        common += group[1..].iter()
+            .fold(group[0].clone(), |total, person| total.intersection(person))
+            .len();
+    }
+    (total, common)
+}
+
+/// Compare answers stored in hash sets, as they used to be, against bitsets. Questions outside
+/// of `a` to `z` aren't stored as bits, so an alphabet mixing both shows what they cost.
+fn answers(c: &mut Criterion) {
+    let letters: Vec<char> = ('a'..='z').collect();
+    let mixed: Vec<char> = ('a'..='m').chain('α'..='μ').collect();
+
+    for (name, alphabet) in [("letters", letters), ("mixed", mixed)] {
+        let lines = groups(&alphabet);
+        let sets: Vec<Vec<HashSet<char>>> = lines.iter()
+            .map(|group| group.iter().map(|line| line.chars().collect()).collect())
+            .collect();
+        let bits: Vec<Vec<Answers>> = lines.iter()
+            .map(|group| group.iter().map(|line| line.chars().collect()).collect())
+            .collect();
+        assert_eq!(hash_sets(&sets), bitsets(&bits), "hash sets and bitsets disagree");
+
+        let mut group = c.benchmark_group(format!("answers {}", name));
+        // each iteration goes through all the groups, so a few samples are enough
+        group.sample_size(10);
+        group.throughput(Throughput::Elements(GROUPS as u64));
+        group.bench_function("hash_set", |b| b.iter(|| hash_sets(black_box(&sets))));
+        group.bench_function("bitset", |b| b.iter(|| bitsets(black_box(&bits))));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, answers);
+criterion_main!(benches);
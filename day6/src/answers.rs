@@ -0,0 +1,130 @@
+use std::collections::BTreeSet;
+use std::iter::FromIterator;
+
+/// Set of questions. Questions `a` to `z` are stored as bits, so sets of those never allocate
+/// and set operations are a single instruction. Any other character is kept aside in a sorted set,
+/// which only grows with the number of such answers, whatever their code points.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Answers {
+    letters: u32,
+    others: BTreeSet<char>
+}
+
+fn letter(index: u32) -> char {
+    (b'a' + index as u8) as char
+}
+
+impl Answers {
+
+    pub fn new() -> Answers {
+        Answers::default()
+    }
+
+    pub fn insert(&mut self, question: char) {
+        match question {
+            'a'..='z' => self.letters |= 1 << (question as u32 - 'a' as u32),
+            _ => {
+                self.others.insert(question);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.letters.count_ones() as usize + self.others.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.letters == 0 && self.others.is_empty()
+    }
+
+    pub fn union(&self, other: &Answers) -> Answers {
+        Answers {
+            letters: self.letters | other.letters,
+            others: self.others.union(&other.others).copied().collect()
+        }
+    }
+
+    pub fn intersection(&self, other: &Answers) -> Answers {
+        Answers {
+            letters: self.letters & other.letters,
+            others: self.others.intersection(&other.others).copied().collect()
+        }
+    }
+
+    pub fn difference(&self, other: &Answers) -> Answers {
+        Answers {
+            letters: self.letters & !other.letters,
+            others: self.others.difference(&other.others).copied().collect()
+        }
+    }
+
+    pub fn symmetric_difference(&self, other: &Answers) -> Answers {
+        Answers {
+            letters: self.letters ^ other.letters,
+            others: self.others.symmetric_difference(&other.others).copied().collect()
+        }
+    }
+
+    /// Questions in the set, `a` to `z` first, then the others in the order of their code points
+    pub fn iter(&self) -> impl Iterator<Item = char> + '_ {
+        (0..26)
+            .filter(move |bit| self.letters & (1 << bit) != 0)
+            .map(letter)
+            .chain(self.others.iter().copied())
+    }
+}
+
+impl FromIterator<char> for Answers {
+    fn from_iter<I: IntoIterator<Item = char>>(questions: I) -> Answers {
+        let mut answers = Answers::new();
+        for question in questions {
+            answers.insert(question);
+        }
+        answers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(text: &str) -> Answers {
+        text.chars().collect()
+    }
+
+    fn text(answers: &Answers) -> String {
+        answers.iter().collect()
+    }
+
+    #[test]
+    fn letters() {
+        let answers = set("zabca");
+        assert_eq!(answers.len(), 4);
+        assert_eq!(text(&answers), "abcz");
+        assert!(answers.others.is_empty());
+        assert!(Answers::new().is_empty());
+    }
+
+    #[test]
+    fn other_characters_stay_small() {
+        let answers = set("a\u{10ffff}Zé🎄b");
+        assert_eq!(answers.len(), 6);
+        assert_eq!(text(&answers), "abZé🎄\u{10ffff}");
+        assert_eq!(answers.others.len(), 4);
+    }
+
+    #[test]
+    fn set_operations() {
+        let (first, second) = (set("abcé🎄"), set("bcdé!"));
+        assert_eq!(text(&first.union(&second)), "abcd!é🎄");
+        assert_eq!(text(&first.intersection(&second)), "bcé");
+        assert_eq!(text(&first.difference(&second)), "a🎄");
+        assert_eq!(text(&first.symmetric_difference(&second)), "ad!🎄");
+    }
+
+    #[test]
+    fn equal_whatever_the_order() {
+        assert_eq!(set("é🎄ba"), set("ab🎄é"));
+        assert_eq!(set("abé").difference(&set("é")), set("ba"));
+    }
+}
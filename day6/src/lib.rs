@@ -0,0 +1,3 @@
+mod answers;
+
+pub use answers::Answers;
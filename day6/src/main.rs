@@ -1,10 +1,25 @@
 use std::env;
 use std::fs::File;
-use std::io::prelude::*;
 use std::io::BufReader;
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::process;
 
-type Person = HashSet<char>;
+use records::{Record, Records};
+
+mod query;
+mod stats;
+
+use day6::Answers;
+use query::Query;
+use stats::Stats;
+
+/// How to output the statistics of each question
+enum Report {
+    Text,
+    Csv
+}
+
+type Person = Answers;
 
 #[derive(Debug)]
 struct Group(Vec<Person>);
@@ -15,55 +30,104 @@ fn parse_person(text: &str) -> Person {
 
 impl Group {
 
-    fn new() -> Group {
-        Group(Vec::new())
-    }
-
-    fn add(&mut self, person: Person) {
-        self.0.push(person);
-    }
-
     fn read(path: &str) -> Vec<Group> {
         let file = File::open(path).expect("cannot open file");
         let buffer = BufReader::new(file);
 
-        let mut groups = vec![Group::new()];
-
-        buffer.lines()
+        Records::new(buffer)
             .map(|result| result.expect("cannot read line"))
-            .for_each(|line| match line.as_str() {
-                "" => groups.push(Group::new()),
-                text => {
-                    let last = groups.last_mut().expect("no groups to add questions");
-                    last.add(parse_person(text))
-                }
-            });
+            .map(|record| Group::parse(&record))
+            .collect()
+    }
 
-        groups
+    /// Each line of the record holds the answers of one person
+    fn parse(record: &Record) -> Group {
+        let people = record.lines.iter()
+            .map(|line| parse_person(line.trim_start()))
+            .collect();
+
+        Group(people)
+    }
+
+    /// Number of people who answered each question
+    fn answers(&self) -> HashMap<char, usize> {
+        let mut answers = HashMap::new();
+        for letter in self.0.iter().flat_map(|person| person.iter()) {
+            *answers.entry(letter).or_insert(0) += 1;
+        }
+        answers
     }
 
     fn total_questions(&self) -> usize {
         self.0.iter()
-            .fold(HashSet::new(), |total, person| {
-                total.union(&person).cloned().collect()
-            })
+            .fold(Answers::new(), |total, person| total.union(person))
             .len()
     }
 
     fn common_questions(&self) -> usize {
         let base = self.0[0].clone();
         self.0[1..].iter()
-            .fold(base, |total, person| {
-                total.intersection(&person).cloned().collect()
-            })
+            .fold(base, |total, person| total.intersection(person))
             .len()
     }
 }
 
 fn main() {
-    let path = env::args().nth(1).expect("no path to file");
+    let mut path = None;
+    let mut queries = Vec::new();
+    let mut per_group = false;
+    let mut report = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--query" => {
+                let text = args.next().expect("missing query");
+                let query = Query::parse(&text).unwrap_or_else(|e| {
+                    eprintln!("invalid query {:?}: {}", text, e);
+                    process::exit(1);
+                });
+                queries.push(query);
+            },
+            "--per-group" => per_group = true,
+            "--stats" => report = match args.next().as_deref() {
+                Some("text") => Some(Report::Text),
+                Some("csv") => Some(Report::Csv),
+                _ => panic!("invalid stats format")
+            },
+            _ => path = Some(arg)
+        }
+    }
+
+    let path = path.expect("no path to file");
     let groups = Group::read(&path);
 
+    if let Some(report) = report {
+        let stats = Stats::new(&groups);
+        match report {
+            Report::Text => stats.print_text(),
+            Report::Csv => stats.print_csv()
+        }
+        return;
+    }
+
+    if !queries.is_empty() {
+        for query in &queries {
+            let mut total = 0;
+            for (i, group) in groups.iter().enumerate() {
+                let questions = query.evaluate(group);
+                if per_group {
+                    let mut letters: Vec<char> = questions.iter().collect();
+                    letters.sort_unstable();
+                    println!("{} group {}: {}", query, i + 1, letters.into_iter().collect::<String>());
+                }
+                total += questions.len();
+            }
+            println!("{} {}", query, total);
+        }
+        return;
+    }
+
     let total: usize = groups.iter()
         .map(|g| g.total_questions())
         .sum();
@@ -74,4 +138,4 @@ fn main() {
 
     println!("total {}", total);
     println!("common {}", common);
-}
\ No newline at end of file
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use expr::Expr;
+
+use crate::{Group, Person};
+
+/// A question asked of each group, written as an expression like
+/// `difference(at_least(2), person(1))`. Evaluating it gives the set of questions it selects.
+#[derive(Debug)]
+pub enum Query {
+    /// answered by anyone in the group
+    Any,
+    /// answered by everyone in the group
+    All,
+    AtLeast(usize),
+    Exactly(usize),
+    /// answers of one person, starting at 1. Empty when the group is smaller.
+    Person(usize),
+    /// the given questions, to compare groups against a fixed set
+    Letters(String),
+    Union(Vec<Query>),
+    Intersection(Vec<Query>),
+    /// questions of the first set that aren't in the second one
+    Difference(Box<Query>, Box<Query>),
+    /// questions in exactly one of the two sets
+    Symmetric(Box<Query>, Box<Query>)
+}
+
+fn count(letters: &HashMap<char, usize>, keep: impl Fn(usize) -> bool) -> Person {
+    letters.iter()
+        .filter(|(_, people)| keep(**people))
+        .map(|(letter, _)| *letter)
+        .collect()
+}
+
+impl Query {
+
+    pub fn parse(text: &str) -> Result<Query, String> {
+        Self::build(&Expr::parse(text)?)
+    }
+
+    fn build(expr: &Expr) -> Result<Query, String> {
+        let (name, args) = match expr {
+            Expr::Call(name, args) => (name.as_str(), args.as_slice()),
+            expr => return Err(format!("{} is not a query", expr))
+        };
+
+        let query = match (name, args) {
+            ("any", []) => Query::Any,
+            ("all", []) => Query::All,
+            ("at_least", [Expr::Number(n)]) => Query::AtLeast(*n),
+            ("exactly", [Expr::Number(n)]) => Query::Exactly(*n),
+            ("person", [Expr::Number(0)]) => return Err("people are counted from 1".to_string()),
+            ("person", [Expr::Number(n)]) => Query::Person(*n),
+            ("letters", [Expr::Text(letters)]) => Query::Letters(letters.clone()),
+            ("union", [_, ..]) => Query::Union(Self::build_all(args)?),
+            ("intersection", [_, ..]) => Query::Intersection(Self::build_all(args)?),
+            ("difference", [first, second]) =>
+                Query::Difference(Box::new(Self::build(first)?), Box::new(Self::build(second)?)),
+            ("symmetric", [first, second]) =>
+                Query::Symmetric(Box::new(Self::build(first)?), Box::new(Self::build(second)?)),
+            ("any" | "all" | "at_least" | "exactly" | "person" | "letters" | "union" | "intersection"
+                | "difference" | "symmetric", _) => return Err(format!("wrong arguments for {}", name)),
+            _ => return Err(format!("unknown function {}", name))
+        };
+
+        Ok(query)
+    }
+
+    fn build_all(exprs: &[Expr]) -> Result<Vec<Query>, String> {
+        exprs.iter().map(Self::build).collect()
+    }
+
+    /// Questions selected in the group. The number of people who answered each question
+    /// is counted once, then shared by every part of the query.
+    pub fn evaluate(&self, group: &Group) -> Person {
+        self.select(group, &group.answers())
+    }
+
+    fn select(&self, group: &Group, answers: &HashMap<char, usize>) -> Person {
+        match self {
+            Query::Any => count(answers, |people| people > 0),
+            Query::All => count(answers, |people| people == group.0.len()),
+            Query::AtLeast(n) => count(answers, |people| people >= *n),
+            Query::Exactly(n) => count(answers, |people| people == *n),
+            Query::Person(n) => group.0.get(n - 1).cloned().unwrap_or_default(),
+            Query::Letters(letters) => letters.chars().collect(),
+            Query::Union(queries) => queries.iter()
+                .fold(Person::new(), |total, query| total.union(&query.select(group, answers))),
+            Query::Intersection(queries) => {
+                let first = queries[0].select(group, answers);
+                queries[1..].iter()
+                    .fold(first, |total, query| total.intersection(&query.select(group, answers)))
+            },
+            Query::Difference(first, second) =>
+                first.select(group, answers).difference(&second.select(group, answers)),
+            Query::Symmetric(first, second) =>
+                first.select(group, answers).symmetric_difference(&second.select(group, answers))
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |queries: &[Query]| {
+            let queries: Vec<String> = queries.iter().map(|q| q.to_string()).collect();
+            queries.join(", ")
+        };
+
+        match self {
+            Query::Any => write!(f, "any"),
+            Query::All => write!(f, "all"),
+            Query::AtLeast(n) => write!(f, "at_least({})", n),
+            Query::Exactly(n) => write!(f, "exactly({})", n),
+            Query::Person(n) => write!(f, "person({})", n),
+            Query::Letters(letters) => write!(f, "letters({:?})", letters),
+            Query::Union(queries) => write!(f, "union({})", list(queries)),
+            Query::Intersection(queries) => write!(f, "intersection({})", list(queries)),
+            Query::Difference(first, second) => write!(f, "difference({}, {})", first, second),
+            Query::Symmetric(first, second) => write!(f, "symmetric({}, {})", first, second)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use records::Records;
+
+    fn sample() -> Vec<Group> {
+        Records::new(include_str!("../input/sample").as_bytes())
+            .map(|record| Group::parse(&record.expect("cannot read record")))
+            .collect()
+    }
+
+    /// Questions selected in each group of the sample, sorted
+    fn evaluate(text: &str) -> Vec<String> {
+        let query = Query::parse(text).expect("cannot parse query");
+        sample().iter()
+            .map(|group| {
+                let mut letters: Vec<char> = query.evaluate(group).iter().collect();
+                letters.sort_unstable();
+                letters.into_iter().collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_and_prints() {
+        for text in ["any", "all", "at_least(2)", "exactly(1)", "person(3)", "letters(\"ab\")",
+            "union(any, all)", "intersection(any, letters(\"xyz\"), at_least(1))",
+            "difference(at_least(2), person(1))", "symmetric(person(1), person(2))"] {
+            assert_eq!(Query::parse(text).expect("cannot parse query").to_string(), text);
+        }
+        assert_eq!(Query::parse("any()").unwrap().to_string(), "any");
+    }
+
+    #[test]
+    fn escapes_like_policies() {
+        match Query::parse(r#"letters("a\"b\\c\d")"#).unwrap() {
+            Query::Letters(letters) => assert_eq!(letters, r#"a"b\c\d"#),
+            query => panic!("unexpected query {:?}", query)
+        }
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        assert_eq!(Query::parse("some").unwrap_err(), "unknown function some");
+        assert_eq!(Query::parse("at_least").unwrap_err(), "wrong arguments for at_least");
+        assert_eq!(Query::parse("any(1)").unwrap_err(), "wrong arguments for any");
+        assert_eq!(Query::parse("union()").unwrap_err(), "wrong arguments for union");
+        assert_eq!(Query::parse("difference(any)").unwrap_err(), "wrong arguments for difference");
+        assert_eq!(Query::parse("person(0)").unwrap_err(), "people are counted from 1");
+        assert_eq!(Query::parse("3").unwrap_err(), "3 is not a query");
+        assert_eq!(Query::parse("union(any, 3)").unwrap_err(), "3 is not a query");
+        assert!(Query::parse("union(any").is_err());
+        assert!(Query::parse("any all").is_err());
+    }
+
+    #[test]
+    fn puzzle_answers() {
+        assert_eq!(evaluate("any"), vec!["abc", "abc", "abc", "a", "b"]);
+        assert_eq!(evaluate("all"), vec!["abc", "", "a", "a", "b"]);
+    }
+
+    #[test]
+    fn counts() {
+        assert_eq!(evaluate("at_least(2)"), vec!["", "", "a", "a", ""]);
+        assert_eq!(evaluate("exactly(1)"), vec!["abc", "abc", "bc", "", "b"]);
+        assert_eq!(evaluate("at_least(0)"), evaluate("any"));
+    }
+
+    #[test]
+    fn people_and_letters() {
+        assert_eq!(evaluate("person(1)"), vec!["abc", "a", "ab", "a", "b"]);
+        assert_eq!(evaluate("person(2)"), vec!["", "b", "ac", "a", ""]);
+        assert_eq!(evaluate("letters(\"ba\")"), vec!["ab"; 5]);
+    }
+
+    #[test]
+    fn set_operations() {
+        assert_eq!(evaluate("union(exactly(1), all)"), vec!["abc", "abc", "abc", "a", "b"]);
+        assert_eq!(evaluate("intersection(any, letters(\"ab\"))"), vec!["ab", "ab", "ab", "a", "b"]);
+        assert_eq!(evaluate("difference(any, person(1))"), vec!["", "bc", "c", "", ""]);
+        assert_eq!(evaluate("symmetric(person(1), person(2))"), vec!["abc", "ab", "bc", "", "b"]);
+    }
+}
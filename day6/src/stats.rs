@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crate::Group;
+
+/// Width of the longest bar in the group size histogram
+const BAR: usize = 40;
+
+#[derive(Debug, Default)]
+struct Question {
+    /// people who answered the question
+    people: usize,
+    /// groups where at least one person answered the question
+    groups: usize,
+    /// groups where everyone answered the question
+    unanimous: usize
+}
+
+/// How each question was answered across all the groups
+#[derive(Debug)]
+pub struct Stats {
+    questions: BTreeMap<char, Question>,
+    /// number of groups for each number of people
+    sizes: BTreeMap<usize, usize>,
+    groups: usize,
+    /// groups where everyone gave the same answers
+    unanimous: usize
+}
+
+impl Question {
+
+    /// Share of the groups that answered the question where everyone did
+    fn unanimity(&self) -> f64 {
+        match self.groups {
+            0 => 0.0,
+            groups => self.unanimous as f64 / groups as f64
+        }
+    }
+}
+
+impl Stats {
+
+    pub fn new(groups: &[Group]) -> Stats {
+        let mut questions: BTreeMap<char, Question> = BTreeMap::new();
+        let mut sizes = BTreeMap::new();
+        let mut unanimous = 0;
+
+        for group in groups {
+            *sizes.entry(group.0.len()).or_insert(0) += 1;
+            if group.total_questions() == group.common_questions() {
+                unanimous += 1;
+            }
+            for (letter, people) in group.answers() {
+                let question = questions.entry(letter).or_default();
+                question.people += people;
+                question.groups += 1;
+                if people == group.0.len() {
+                    question.unanimous += 1;
+                }
+            }
+        }
+
+        Stats { questions, sizes, groups: groups.len(), unanimous }
+    }
+
+    /// Share of the groups where everyone gave the same answers
+    fn unanimity(&self) -> f64 {
+        match self.groups {
+            0 => 0.0,
+            groups => self.unanimous as f64 / groups as f64
+        }
+    }
+
+    pub fn print_text(&self) {
+        println!("{:<8} {:>8} {:>8} {:>10} {:>10}", "question", "people", "groups", "unanimous", "unanimity");
+        for (letter, question) in &self.questions {
+            println!(
+                "{:<8} {:>8} {:>8} {:>10} {:>9.1}%",
+                letter,
+                question.people,
+                question.groups,
+                question.unanimous,
+                question.unanimity() * 100.0
+            );
+        }
+
+        println!();
+        println!(
+            "{} groups, {} with unanimous answers ({:.1}%)",
+            self.groups,
+            self.unanimous,
+            self.unanimity() * 100.0
+        );
+        println!("{:>4} {:>8}", "size", "groups");
+        let largest = self.sizes.values().copied().max().unwrap_or(0);
+        for (size, groups) in &self.sizes {
+            let bar = (groups * BAR).div_ceil(largest);
+            println!("{:>4} {:>8} {}", size, groups, "#".repeat(bar));
+        }
+    }
+
+    /// Three tables separated by blank lines: one row per question, a single row summing up
+    /// the groups, then one row per group size
+    pub fn print_csv(&self) {
+        println!("question,people,groups,unanimous,unanimity");
+        for (letter, question) in &self.questions {
+            println!(
+                "{},{},{},{},{:.4}",
+                letter,
+                question.people,
+                question.groups,
+                question.unanimous,
+                question.unanimity()
+            );
+        }
+
+        println!();
+        println!("groups,unanimous,unanimity");
+        println!("{},{},{:.4}", self.groups, self.unanimous, self.unanimity());
+
+        println!();
+        println!("size,groups");
+        for (size, groups) in &self.sizes {
+            println!("{},{}", size, groups);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use records::Records;
+
+    fn sample() -> Stats {
+        let groups: Vec<Group> = Records::new(include_str!("../input/sample").as_bytes())
+            .map(|record| Group::parse(&record.expect("cannot read record")))
+            .collect();
+        Stats::new(&groups)
+    }
+
+    #[test]
+    fn groups() {
+        let stats = sample();
+        assert_eq!(stats.groups, 5);
+        assert_eq!(stats.unanimous, 3);
+        assert_eq!(stats.unanimity(), 0.6);
+        assert_eq!(stats.sizes.into_iter().collect::<Vec<_>>(), vec![(1, 2), (2, 1), (3, 1), (4, 1)]);
+    }
+
+    #[test]
+    fn questions() {
+        let stats = sample();
+        let a = &stats.questions[&'a'];
+        assert_eq!((a.people, a.groups, a.unanimous), (8, 4, 3));
+        assert_eq!(a.unanimity(), 0.75);
+        let c = &stats.questions[&'c'];
+        assert_eq!((c.people, c.groups, c.unanimous), (3, 3, 1));
+    }
+
+    #[test]
+    fn no_groups() {
+        let stats = Stats::new(&[]);
+        assert_eq!(stats.unanimity(), 0.0);
+        assert!(stats.questions.is_empty());
+    }
+}
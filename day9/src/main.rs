@@ -3,6 +3,8 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 
+use day1::Combinations;
+
 fn parse(path: &str) -> Vec<u64> {
     let file = File::open(path).expect("cannot open file");
     let buffer = BufReader::new(file);
@@ -28,15 +30,8 @@ fn find_invalid(preamble: usize, numbers: &Vec<u64>) -> u64 {
 }
 
 fn is_valid(needle: u64, numbers: &[u64]) -> bool {
-    numbers.iter()
-        .enumerate()
-        .any(|(i, outer)| {
-            numbers.iter()
-                .enumerate()
-                .any(|(j, inner)| {
-                    i != j && outer + inner == needle
-                })
-        })
+    Combinations::new(numbers, 2)
+        .any(|pair| pair[0] + pair[1] == needle)
 }
 
 fn find_sum(invalid: u64, numbers: &Vec<u64>) -> Vec<u64> {
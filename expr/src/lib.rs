@@ -0,0 +1,208 @@
+use std::fmt;
+
+/// A function call written like `all(count, not(forbidden("xyz")), regex("^[a-z]+$"))`.
+/// Names without parentheses are calls without arguments.
+#[derive(Debug)]
+pub enum Expr {
+    Call(String, Vec<Expr>),
+    Number(usize),
+    Text(String)
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Call(name, args) if args.is_empty() => write!(f, "{}", name),
+            Expr::Call(name, args) => {
+                let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            },
+            Expr::Number(number) => write!(f, "{}", number),
+            Expr::Text(text) => write!(f, "{:?}", text)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Number(usize),
+    Text(String),
+    Open,
+    Close,
+    Comma
+}
+
+/// Split an expression into tokens. Everything after a `#` is a comment, up to the end of the line.
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            '#' => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+            },
+            '"' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // only quotes and backslashes are escaped, so that patterns like `\d` keep their backslash
+                        Some('\\') => match chars.next_if(|c| *c == '"' || *c == '\\') {
+                            Some(escaped) => quoted.push(escaped),
+                            None => quoted.push('\\')
+                        },
+                        Some(c) => quoted.push(c),
+                        None => return Err("unterminated string".to_string())
+                    }
+                }
+                tokens.push(Token::Text(quoted));
+            },
+            c if c.is_whitespace() => {},
+            c if c.is_ascii_digit() => {
+                let mut digits = c.to_string();
+                while let Some(d) = chars.next_if(|d| d.is_ascii_digit()) {
+                    digits.push(d);
+                }
+                let number = digits.parse().map_err(|_| format!("cannot parse number {}", digits))?;
+                tokens.push(Token::Number(number));
+            },
+            c if c.is_alphabetic() => {
+                let mut name = c.to_string();
+                while let Some(n) = chars.next_if(|n| n.is_alphanumeric() || *n == '_' || *n == '-') {
+                    name.push(n);
+                }
+                tokens.push(Token::Name(name));
+            },
+            c => return Err(format!("unexpected character {:?}", c))
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_expr(tokens: &[Token], position: &mut usize) -> Result<Expr, String> {
+    let token = tokens.get(*position).ok_or("unexpected end of expression")?;
+    *position += 1;
+
+    let name = match token {
+        Token::Number(number) => return Ok(Expr::Number(*number)),
+        Token::Text(text) => return Ok(Expr::Text(text.clone())),
+        Token::Name(name) => name.clone(),
+        token => return Err(format!("unexpected {:?}", token))
+    };
+
+    let mut args = Vec::new();
+    if tokens.get(*position) == Some(&Token::Open) {
+        *position += 1;
+        if tokens.get(*position) == Some(&Token::Close) {
+            *position += 1;
+        } else {
+            loop {
+                args.push(parse_expr(tokens, position)?);
+                let token = tokens.get(*position).ok_or("missing closing parenthesis")?;
+                *position += 1;
+                match token {
+                    Token::Comma => continue,
+                    Token::Close => break,
+                    token => return Err(format!("unexpected {:?}", token))
+                }
+            }
+        }
+    }
+
+    Ok(Expr::Call(name, args))
+}
+
+impl Expr {
+
+    pub fn parse(text: &str) -> Result<Expr, String> {
+        let tokens = tokenize(text)?;
+        let mut position = 0;
+        let expr = parse_expr(&tokens, &mut position)?;
+        match tokens.get(position) {
+            Some(token) => Err(format!("unexpected {:?} after expression", token)),
+            None => Ok(expr)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_calls() {
+        let expr = Expr::parse("all(count, not(forbidden(\"xyz\")), distinct(3))").unwrap();
+        assert_eq!(expr.to_string(), "all(count, not(forbidden(\"xyz\")), distinct(3))");
+    }
+
+    #[test]
+    fn skips_comments_and_whitespace() {
+        let expr = Expr::parse("any( # either rule\n  count,\n  position # or this one\n)").unwrap();
+        assert_eq!(expr.to_string(), "any(count, position)");
+    }
+
+    #[test]
+    fn keeps_backslashes_that_are_not_escapes() {
+        match Expr::parse(r#"regex("^\d+$")"#).unwrap() {
+            Expr::Call(_, args) => match &args[..] {
+                [Expr::Text(pattern)] => assert_eq!(pattern, r"^\d+$"),
+                args => panic!("unexpected arguments {:?}", args)
+            },
+            expr => panic!("unexpected expression {:?}", expr)
+        }
+    }
+
+    #[test]
+    fn escapes_quotes_and_backslashes() {
+        match Expr::parse(r#"forbidden("a\"b\\c")"#).unwrap() {
+            Expr::Call(_, args) => match &args[..] {
+                [Expr::Text(letters)] => assert_eq!(letters, r#"a"b\c"#),
+                args => panic!("unexpected arguments {:?}", args)
+            },
+            expr => panic!("unexpected expression {:?}", expr)
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        assert!(Expr::parse("all(count").is_err());
+        assert!(Expr::parse("count)").is_err());
+        assert!(Expr::parse("regex(\"abc").is_err());
+        assert!(Expr::parse("count;").is_err());
+        assert!(Expr::parse("").is_err());
+    }
+
+    #[test]
+    fn numbers_and_strings() {
+        match Expr::parse("at_least(2, \"ab\")").unwrap() {
+            Expr::Call(name, args) => {
+                assert_eq!(name, "at_least");
+                match &args[..] {
+                    [Expr::Number(2), Expr::Text(text)] => assert_eq!(text, "ab"),
+                    args => panic!("unexpected arguments {:?}", args)
+                }
+            },
+            expr => panic!("unexpected expression {:?}", expr)
+        }
+    }
+
+    #[test]
+    fn empty_parentheses() {
+        let expr = Expr::parse("any()").unwrap();
+        assert_eq!(expr.to_string(), "any");
+    }
+
+    #[test]
+    fn names() {
+        let expr = Expr::parse("at_least(1) ").unwrap();
+        assert_eq!(expr.to_string(), "at_least(1)");
+        let expr = Expr::parse("no-digits").unwrap();
+        assert_eq!(expr.to_string(), "no-digits");
+    }
+}
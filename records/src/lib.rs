@@ -0,0 +1,159 @@
+use std::io;
+use std::io::BufRead;
+
+/// Lines of a record, as found between blank lines
+#[derive(Debug)]
+pub struct Record {
+    /// position of the record in the input, starting at 1
+    pub index: usize,
+    /// line where the record starts, starting at 1
+    pub line: usize,
+    /// lines of the record without their trailing whitespace
+    pub lines: Vec<String>
+}
+
+/// Iterator over the records of an input where records are separated by blank lines.
+///
+/// Lines are read one at a time, so only the current record is kept in memory.
+/// Windows line endings and trailing whitespace are ignored, and a line made only of
+/// whitespace counts as blank. Several blank lines in a row, or blank lines at the start
+/// or the end of the input, don't produce empty records.
+pub struct Records<R> {
+    reader: R,
+    /// number of lines read so far
+    line: usize,
+    /// number of records yielded so far
+    count: usize,
+    buffer: String
+}
+
+impl Record {
+
+    /// Words of the record, separated by any whitespace
+    pub fn tokens(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().flat_map(|line| line.split_whitespace())
+    }
+}
+
+impl<R: BufRead> Records<R> {
+
+    pub fn new(reader: R) -> Records<R> {
+        Records {
+            reader,
+            line: 0,
+            count: 0,
+            buffer: String::new()
+        }
+    }
+
+    /// Next line without its trailing whitespace, or None at the end of the input
+    fn read_line(&mut self) -> io::Result<Option<&str>> {
+        self.buffer.clear();
+        match self.reader.read_line(&mut self.buffer)? {
+            0 => Ok(None),
+            _ => {
+                self.line += 1;
+                Ok(Some(self.buffer.trim_end()))
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record: Option<Record> = None;
+
+        loop {
+            let line = match self.read_line() {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error))
+            };
+
+            match (line, record.as_mut()) {
+                (None, _) => break,
+                (Some(""), None) => continue,
+                (Some(""), Some(_)) => break,
+                (Some(text), Some(record)) => record.lines.push(text.to_string()),
+                (Some(text), None) => {
+                    let text = text.to_string();
+                    self.count += 1;
+                    record = Some(Record { index: self.count, line: self.line, lines: vec![text] });
+                }
+            }
+        }
+
+        record.map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Starting line of each record, and its lines joined with `|`
+    fn records(text: &str) -> Vec<(usize, String)> {
+        let records: Vec<Record> = Records::new(text.as_bytes())
+            .collect::<io::Result<_>>()
+            .expect("cannot read records");
+
+        records.iter()
+            .enumerate()
+            .map(|(i, record)| {
+                assert_eq!(record.index, i + 1);
+                (record.line, record.lines.join("|"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(records(""), vec![]);
+        assert_eq!(records("\n\n  \n"), vec![]);
+    }
+
+    #[test]
+    fn single_line() {
+        assert_eq!(records("a b"), vec![(1, "a b".to_string())]);
+    }
+
+    #[test]
+    fn separated_by_blank_lines() {
+        assert_eq!(records("a\nb\n\nc\n"), vec![(1, "a|b".to_string()), (4, "c".to_string())]);
+    }
+
+    #[test]
+    fn windows_line_endings() {
+        assert_eq!(records("a\r\nb\r\n\r\nc\r\n"), vec![(1, "a|b".to_string()), (4, "c".to_string())]);
+    }
+
+    #[test]
+    fn several_blank_lines() {
+        assert_eq!(records("\n\na\n\n\n\nb\n"), vec![(3, "a".to_string()), (7, "b".to_string())]);
+    }
+
+    #[test]
+    fn trailing_whitespace() {
+        assert_eq!(records("a  \t\nb \n \t \nc"), vec![(1, "a|b".to_string()), (4, "c".to_string())]);
+    }
+
+    #[test]
+    fn trailing_blank_line() {
+        assert_eq!(records("a\n\n"), vec![(1, "a".to_string())]);
+        assert_eq!(records("a\n\nb\n\n\n"), vec![(1, "a".to_string()), (3, "b".to_string())]);
+    }
+
+    #[test]
+    fn tokens() {
+        let record = Records::new("a:1  b:2\n\tc:3\n".as_bytes()).next().unwrap().unwrap();
+        let tokens: Vec<&str> = record.tokens().collect();
+        assert_eq!(tokens, vec!["a:1", "b:2", "c:3"]);
+    }
+
+    #[test]
+    fn read_error() {
+        let mut records = Records::new(&[b'a', 0xff, b'\n'][..]);
+        assert!(records.next().unwrap().is_err());
+    }
+}
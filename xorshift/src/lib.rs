@@ -0,0 +1,83 @@
+/// Seed used when none is given, so that benchmarks always see the same inputs
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Xorshift random number generator. Good enough to spread benchmark inputs around,
+/// and the same seed always gives the same numbers.
+#[derive(Debug, Clone)]
+pub struct XorShift {
+    state: u64
+}
+
+impl XorShift {
+
+    /// A zero state would only ever produce zeroes, so a zero seed is replaced by the default one
+    pub fn new(seed: u64) -> XorShift {
+        match seed {
+            0 => XorShift { state: SEED },
+            seed => XorShift { state: seed }
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Number from 0 up to `limit`, excluded. The modulo leans slightly towards small numbers
+    /// when `limit` isn't a power of two, which doesn't matter for benchmarks.
+    pub fn below(&mut self, limit: u64) -> u64 {
+        self.next_u64() % limit
+    }
+}
+
+impl Default for XorShift {
+    fn default() -> XorShift {
+        XorShift::new(SEED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut first = XorShift::new(42);
+        let mut second = first.clone();
+        for _ in 0..100 {
+            assert_eq!(first.next_u64(), second.next_u64());
+        }
+    }
+
+    #[test]
+    fn known_values() {
+        let mut random = XorShift::new(1);
+        assert_eq!(random.next_u64(), 1082269761);
+        assert_eq!(random.next_u64(), 1152992998833853505);
+    }
+
+    #[test]
+    fn zero_seed() {
+        let mut zero = XorShift::new(0);
+        let mut default = XorShift::default();
+        for _ in 0..10 {
+            let number = zero.next_u64();
+            assert_ne!(number, 0);
+            assert_eq!(number, default.next_u64());
+        }
+    }
+
+    #[test]
+    fn below_limit() {
+        let mut random = XorShift::default();
+        let mut seen = [false; 10];
+        for _ in 0..1000 {
+            let number = random.below(10);
+            assert!(number < 10);
+            seen[number as usize] = true;
+        }
+        assert!(seen.iter().all(|seen| *seen));
+    }
+}